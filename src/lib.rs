@@ -1,11 +1,135 @@
 #![cfg_attr(not(any(test, feature = "std")), no_std)]
 
 use ink_core::{
-    env::{self, AccountId},
+    env::{self, AccountId, Balance},
+    memory::vec::Vec,
     storage,
 };
 use ink_lang::contract;
+use core::convert::TryFrom;
 use parity_codec::{Decode, Encode};
+use tiny_keccak::keccak256;
+use ed25519_dalek::{PublicKey, Signature};
+use sha2::Sha512;
+
+/// Reasons an operation can be rejected
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub enum Error {
+    /// The token was never minted or no longer exists
+    TokenNotFound,
+    /// The account does not own the token
+    NotOwner,
+    /// The token would be transferred to its current owner
+    SelfTransfer,
+    /// The contract is paused
+    Paused,
+    /// The token cannot be moved right now (locked, staked, frozen, recipient not allowed)
+    TransferNotAllowed,
+    /// The tokens cannot be minted right now (minting disabled, supply or block cap reached)
+    MintNotAllowed,
+}
+
+/// Operations reported by `EventOperationFailed`
+#[derive(Clone, Copy)]
+enum OpCode {
+    Transfer = 1,
+    TransferFrom = 2,
+    Mint = 3,
+    BatchMint = 4,
+    Approval = 5,
+}
+
+/// The zero address, used as the "nobody" sentinel
+fn zero_account() -> AccountId {
+    AccountId::try_from([0x0; 32]).unwrap()
+}
+
+/// Whether an account is the zero address
+fn is_zero(account: &AccountId) -> bool {
+    *account == zero_account()
+}
+
+/// Number of entries a batch call accepts until the owner changes it
+const DEFAULT_MAX_BATCH_SIZE: u64 = 50;
+
+/// Longest URI accepted until the owner changes it
+const DEFAULT_MAX_URI_LEN: u32 = 256;
+
+/// Deepest level a token can be attached at until the owner changes it
+const DEFAULT_MAX_NESTING_DEPTH: u8 = 5;
+
+/// Number of most recent owners kept in the history of a token
+const MAX_HISTORY_LEN: usize = 32;
+
+/// Appends the decimal representation of n to out
+fn append_decimal(out: &mut Vec<u8>, n: u64) {
+    let mut digits = [0u8; 20];
+    let mut len = 0;
+    let mut rest = n;
+    loop {
+        digits[len] = b'0' + (rest % 10) as u8;
+        len += 1;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+    for index in (0..len).rev() {
+        out.push(digits[index]);
+    }
+}
+
+/// Appends bytes to out as a JSON string, escaping quotes, backslashes and control characters
+fn append_json_string(out: &mut Vec<u8>, bytes: &[u8]) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    out.push(b'"');
+    for byte in bytes {
+        match *byte {
+            b'"' => out.extend_from_slice(b"\\\""),
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            byte if byte < 0x20 => {
+                out.extend_from_slice(b"\\u00");
+                out.push(HEX[(byte >> 4) as usize]);
+                out.push(HEX[(byte & 0xf) as usize]);
+            }
+            byte => out.push(byte),
+        }
+    }
+    out.push(b'"');
+}
+
+/// Next value of a splitmix64 generator
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Deterministic Fisher-Yates shuffle of 1..=n driven by seed
+fn shuffled_indices(n: u64, seed: &[u8; 32]) -> Vec<u64> {
+    // fold the seed into the generator state
+    let mut state = 0u64;
+    for chunk in seed.chunks(8) {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(chunk);
+        state ^= u64::from_le_bytes(word);
+        splitmix64(&mut state);
+    }
+
+    let mut indices: Vec<u64> = (1..=n).collect();
+    for i in (1..indices.len()).rev() {
+        let j = (splitmix64(&mut state) % (i as u64 + 1)) as usize;
+        indices.swap(i, j);
+    }
+    indices
+}
+
+/// Sale phases, see `set_sale_phase`
+const PHASE_CLOSED: u8 = 0;
+const PHASE_ALLOWLIST: u8 = 1;
+const PHASE_PUBLIC: u8 = 2;
 
 contract! {
 
@@ -13,49 +137,404 @@ contract! {
     struct NFToken {
         /// Owner of contract
         owner: storage::Value<AccountId>,
+        /// Name of the collection
+        name: storage::Value<Vec<u8>>,
+        /// Symbol of the collection
+        symbol: storage::Value<Vec<u8>>,
         /// Total tokens minted
         total_minted: storage::Value<u64>,
-        /// Mapping: token_id(u64) -> owner (AccountID)
-        id_to_owner: storage::HashMap<u64, AccountId>,
+        /// Tokens currently in existence (minted minus burned)
+        total_supply: storage::Value<u64>,
+        /// Maximum number of tokens that can ever be minted, 0 if unlimited
+        max_supply: storage::Value<u64>,
+        /// Mapping: token_id(u64) -> (owner(AccountId), approved account(AccountId), zero address if none,
+        /// approval expiry timestamp(u64), 0 if none)
+        ///
+        /// The owner and the single-token approval share one entry, so that
+        /// transfer_from authorizes and moves a token with one read and one write.
+        token_data: storage::HashMap<u64, (AccountId, AccountId, u64)>,
         /// Mapping: owner(AccountID) => tokenCount (u64)
         owner_to_token_count: storage::HashMap<AccountId, u64>,
-        /// Mapping: token_id(u64) to account(AccountId)
-        approvals: storage::HashMap<u64, AccountId>,
+        /// Mapping: (owner, operator) -> approved to move all of owner's tokens (bool)
+        operator_approvals: storage::HashMap<(AccountId, AccountId), bool>,
+        /// Mapping: owner(AccountId) -> number of approved operators (u64)
+        operator_count: storage::HashMap<AccountId, u64>,
+        /// Mapping: token_id(u64) -> timestamp of last mint or transfer (u64)
+        last_transfer: storage::HashMap<u64, u64>,
+        /// Whether transfers and mints are paused
+        paused: storage::Value<bool>,
+        /// Timestamp after which a pause lifts by itself, 0 if it never does
+        pause_expiry: storage::Value<u64>,
+        /// Why the contract was paused, empty when not paused
+        pause_reason: storage::Value<Vec<u8>>,
+        /// Mapping: index(u64) -> holder with a non-zero balance (AccountId)
+        holders: storage::HashMap<u64, AccountId>,
+        /// Mapping: holder(AccountId) -> index in holders (u64)
+        holder_index: storage::HashMap<AccountId, u64>,
+        /// Number of accounts holding at least one token
+        holder_count: storage::Value<u64>,
+        /// Native token fee required with every transfer
+        transfer_fee: storage::Value<u64>,
+        /// Receiver of transfer fees and royalties (treasury)
+        royalty_recipient: storage::Value<AccountId>,
+        /// Default royalty of newly minted tokens, in basis points
+        royalty_bps: storage::Value<u16>,
+        /// Mapping: account(AccountId) -> native tokens it can withdraw (u64)
+        pending_payments: storage::HashMap<AccountId, Balance>,
+        /// Mapping: recipient(AccountId) -> allowed to receive transfers (bool)
+        recipient_allowed: storage::HashMap<AccountId, bool>,
+        /// Whether transfers are restricted to allowed recipients
+        enforce_allowlist: storage::Value<bool>,
+        /// Mapping: external reference([u8; 32]) -> token_id (u64)
+        ref_to_id: storage::HashMap<[u8; 32], u64>,
+        /// Mapping: token_id(u64) -> external reference ([u8; 32])
+        id_to_ref: storage::HashMap<u64, [u8; 32]>,
+        /// Operator approved for every receiver of newly minted tokens, zero address if none
+        auto_operator: storage::Value<AccountId>,
+        /// Mapping: token_id(u64) -> metadata URI (Vec<u8>)
+        token_uris: storage::HashMap<u64, Vec<u8>>,
+        /// Mapping: token_id(u64) -> hash of the off-chain metadata ([u8; 32])
+        token_content_hash: storage::HashMap<u64, [u8; 32]>,
+        /// Lowest token id that may still be free for sequential minting
+        next_token_id: storage::Value<u64>,
+        /// Current sale phase: 0 = closed, 1 = allowlist, 2 = public
+        sale_phase: storage::Value<u8>,
+        /// Mapping: account(AccountId) -> allowed to mint in the allowlist phase (bool)
+        mint_allowlist: storage::HashMap<AccountId, bool>,
+        /// Mapping: token_id(u64) -> operator holding a sale lock on it (AccountId)
+        locked_by: storage::HashMap<u64, AccountId>,
+        /// Whether minting emits a Transfer from the zero address per token
+        emit_per_token_events: storage::Value<bool>,
+        /// Mapping: token_id(u64) -> timestamp it was staked at (u64)
+        staked_at: storage::HashMap<u64, u64>,
+        /// Mapping: token_id(u64) -> account that staked it (AccountId)
+        stakers: storage::HashMap<u64, AccountId>,
+        /// Mapping: account(AccountId) -> tokens ever minted to it (u64)
+        minted_to: storage::HashMap<AccountId, u64>,
+        /// Maximum number of entries accepted by batch calls
+        max_batch_size: storage::Value<u64>,
+        /// Mapping: account(AccountId) -> opted in to receiving tokens (bool)
+        can_receive: storage::HashMap<AccountId, bool>,
+        /// Whether transfers are restricted to accounts that opted in
+        enforce_receiver_optin: storage::Value<bool>,
+        /// Number of tokens with a stored single-token approval
+        total_active_approvals: storage::Value<u64>,
+        /// Mapping: token_id(u64) -> (seller(AccountId), price(u64), expiry timestamp(u64), 0 if none)
+        listings: storage::HashMap<u64, (AccountId, u64, u64)>,
+        /// URI prefix of tokens without their own URI
+        base_uri: storage::Value<Vec<u8>>,
+        /// Longest URI accepted, in bytes
+        max_uri_len: storage::Value<u32>,
+        /// Mapping: index(u64) -> token_id (u64) of every live token
+        all_tokens: storage::HashMap<u64, u64>,
+        /// Mapping: token_id(u64) -> index in all_tokens (u64)
+        all_tokens_index: storage::HashMap<u64, u64>,
+        /// Native token price per token of sale mints
+        mint_price: storage::Value<u64>,
+        /// Most tokens ever minted to an address for sale mints to succeed, 0 if unlimited
+        max_per_address: storage::Value<u64>,
+        /// Sale mint payments not withdrawn yet
+        mint_proceeds: storage::Value<u64>,
+        /// Whether the metadata indices have been shuffled
+        revealed: storage::Value<bool>,
+        /// Mapping: token_id(u64) -> shuffled metadata index (u64)
+        reveal_index: storage::HashMap<u64, u64>,
+        /// Payees of the mint proceeds with their shares in basis points, summing to 10000
+        payees: storage::Value<Vec<(AccountId, u16)>>,
+        /// Mapping: (owner(AccountId), index(u64)) -> token_id (u64) of every token of an owner
+        owned_tokens: storage::HashMap<(AccountId, u64), u64>,
+        /// Mapping: token_id(u64) -> index in owned_tokens of its owner (u64)
+        owned_tokens_index: storage::HashMap<u64, u64>,
+        /// Mapping: owner(AccountId) -> number of entries in owned_tokens (u64)
+        owned_tokens_count: storage::HashMap<AccountId, u64>,
+        /// Mapping: (owner(AccountId), index(u64)) -> operator (AccountId), operator_count entries per owner
+        operators_of: storage::HashMap<(AccountId, u64), AccountId>,
+        /// Mapping: (owner, operator) -> index in operators_of (u64)
+        operators_index: storage::HashMap<(AccountId, AccountId), u64>,
+        /// Account that deployed the contract, never changes
+        deployer: storage::Value<AccountId>,
+        /// Mapping: minter(AccountId) -> index in minter_list (u64) of accounts allowed to mint
+        minters: storage::HashMap<AccountId, u64>,
+        /// Mapping: index(u64) -> minter (AccountId)
+        minter_list: storage::HashMap<u64, AccountId>,
+        /// Number of enabled minters
+        minter_count: storage::Value<u64>,
+        /// Most tokens minted within a single block, 0 if unlimited
+        max_mints_per_block: storage::Value<u64>,
+        /// (block number, tokens minted in that block) of the latest block with mints
+        block_mint_count: storage::Value<(u64, u64)>,
+        /// Mapping: token_id(u64) -> frozen by the contract owner, cannot move (bool)
+        frozen: storage::HashMap<u64, bool>,
+        /// Mapping: token_id(u64) -> has been burned (bool)
+        burned: storage::HashMap<u64, bool>,
+        /// Mapping: token_id(u64) -> royalty in basis points (u16), the default royalty when it was minted
+        token_royalties: storage::HashMap<u64, u16>,
+        /// Mapping: (owner, spender) -> number of owner's tokens, any ids, spender may still move (u64)
+        quantity_approvals: storage::HashMap<(AccountId, AccountId), u64>,
+        /// Whether new tokens can be minted, transfers are not affected
+        minting_enabled: storage::Value<bool>,
+        /// Id of the latest balance snapshot, 0 before the first one
+        snapshot_id: storage::Value<u64>,
+        /// Mapping: (account(AccountId), index(u64)) -> (snapshot id(u64), balance at that snapshot(u64))
+        balance_checkpoints: storage::HashMap<(AccountId, u64), (u64, u64)>,
+        /// Mapping: account(AccountId) -> number of balance_checkpoints (u64)
+        checkpoint_count: storage::HashMap<AccountId, u64>,
+        /// Mapping: token_id(u64) -> price of its latest recorded sale (u64)
+        last_sale_price: storage::HashMap<u64, u64>,
+        /// Account that can take over the contract ownership, zero address if none
+        recovery: storage::Value<AccountId>,
+        /// Time that has to pass between initiating and completing a recovery
+        recovery_delay: storage::Value<u64>,
+        /// Timestamp the pending recovery was initiated at, 0 if none is pending
+        recovery_started_at: storage::Value<u64>,
+        /// Mapping: token_id(u64) -> (owner(AccountId), recipient(AccountId), keccak256 hashlock([u8; 32]),
+        /// expiry timestamp(u64)) of tokens escrowed until the preimage is revealed
+        hashlocks: storage::HashMap<u64, (AccountId, AccountId, [u8; 32], u64)>,
+        /// Time a token has to stay put after it was minted or moved, 0 if none
+        transfer_cooldown: storage::Value<u64>,
+        /// Whether token URIs, the base URI and the reveal can no longer change
+        metadata_frozen: storage::Value<bool>,
+        /// Whether owners cannot move their tokens themselves, only approved spenders and operators can
+        restrict_to_operators: storage::Value<bool>,
+        /// Mapping: index(u64) -> (first token id(u64), last token id(u64)) of ranges revealed with their own base URI
+        reveal_ranges: storage::HashMap<u64, (u64, u64)>,
+        /// Mapping: index(u64) -> base URI (Vec<u8>) of the revealed range at that index
+        reveal_range_uris: storage::HashMap<u64, Vec<u8>>,
+        /// Number of revealed ranges
+        reveal_range_count: storage::Value<u64>,
+        /// Whether approvals are single-use: a spender that used one cannot be re-approved for
+        /// the same token by the same owner during reapproval_cooldown
+        consume_approval: storage::Value<bool>,
+        /// Time a used approval blocks re-approving the same spender
+        reapproval_cooldown: storage::Value<u64>,
+        /// Mapping: (owner, token_id, spender) -> timestamp the spender used its approval (u64)
+        consumed_approvals: storage::HashMap<(AccountId, u64, AccountId), u64>,
+        /// Native tokens ever earned by the collection: sale mint prices, transfer fees and royalties
+        total_revenue: storage::Value<u64>,
+        /// Mapping: minter(AccountId) -> tokens it may still mint through a delegated quota (u64)
+        mint_quotas: storage::HashMap<AccountId, u64>,
+        /// Mapping: token_id(u64) -> (seller(AccountId), reserve price(u64), end timestamp(u64),
+        /// top bidder(AccountId), zero address if none, top bid(u64)) of running auctions
+        auctions: storage::HashMap<u64, (AccountId, u64, u64, AccountId, u64)>,
+        /// Mapping: child token_id(u64) -> parent token_id(u64) of attached tokens
+        parent_of: storage::HashMap<u64, u64>,
+        /// Mapping: parent token_id(u64) -> number of tokens attached to it (u64)
+        child_count: storage::HashMap<u64, u64>,
+        /// Mapping: (parent token_id(u64), index(u64)) -> token_id(u64) attached to it
+        children: storage::HashMap<(u64, u64), u64>,
+        /// Mapping: child token_id(u64) -> index(u64) in the children of its parent
+        children_index: storage::HashMap<u64, u64>,
+        /// Deepest level a token can be attached at, a root parent being level 0
+        max_nesting_depth: storage::Value<u8>,
+        /// Whether tokens can be burned, fixed at deployment
+        burning_enabled: storage::Value<bool>,
+        /// Mapping: token_id(u64) -> owners of the token, oldest first, capped to MAX_HISTORY_LEN
+        token_history: storage::HashMap<u64, Vec<AccountId>>,
+        /// Mapping: token_id(u64) -> (shareholder(AccountId), share in basis points(u16)) of co-owned tokens
+        token_shares: storage::HashMap<u64, Vec<(AccountId, u16)>>,
+        /// Mapping: (token_id(u64), shareholder(AccountId)) -> whether it agreed to the next transfer (bool)
+        share_approvals: storage::HashMap<(u64, AccountId), bool>,
+        /// Id given to the first minted token, fixed at deployment
+        start_token_id: storage::Value<u64>,
+        /// Mapping: token_id(u64) -> account that minted the token (AccountId)
+        creators: storage::HashMap<u64, AccountId>,
+        /// Lowest royalty that can be set, in basis points, fixed at deployment
+        royalty_floor_bps: storage::Value<u16>,
+        /// Mapping: (token_id(u64), index(u64)) -> (key(Vec<u8>), value(Vec<u8>)) of on-chain attributes
+        token_attributes: storage::HashMap<(u64, u64), (Vec<u8>, Vec<u8>)>,
+        /// Mapping: token_id(u64) -> number of attributes it has (u64)
+        attribute_count: storage::HashMap<u64, u64>,
+        /// Mapping: hash of a redeemed voucher([u8; 32]) -> it was used (bool)
+        used_vouchers: storage::HashMap<[u8; 32], bool>,
+        /// Mapping: (owner(AccountId), index(u64)) -> spender with a quantity approval (AccountId)
+        quantity_spenders_of: storage::HashMap<(AccountId, u64), AccountId>,
+        /// Mapping: (owner, spender) -> index in quantity_spenders_of (u64)
+        quantity_spenders_index: storage::HashMap<(AccountId, AccountId), u64>,
+        /// Mapping: owner(AccountId) -> number of spenders with a quantity approval (u64)
+        quantity_spender_count: storage::HashMap<AccountId, u64>,
+        /// ed25519 public key that signs mint vouchers, all zeroes while voucher minting is off
+        voucher_signer: storage::Value<[u8; 32]>,
     }
 
     /// compulsary deploy method
     impl Deploy for NFToken {
         /// Initializes our initial total minted value to 0.
-        fn deploy(&mut self, init_value: u64) {
+        /// A max_supply of 0 leaves the supply uncapped.
+        /// The initial tokens are minted through the regular mint path, so
+        /// they are logged with the same EventMint and Transfer events.
+        /// Token ids count up from start_token_id; with a start of 0, the 0
+        /// that lookups return for "none" is also a valid id.
+        /// No royalty can ever be set below royalty_floor_bps, which is also
+        /// the initial default royalty.
+        /// The contract is owned by owner, or by the caller when None, and a
+        /// zero owner fails the deployment, as does an init_value above max_supply.
+        fn deploy(&mut self, init_value: u64, max_supply: u64, name: Vec<u8>, symbol: Vec<u8>, burning_enabled: bool, start_token_id: u64, royalty_floor_bps: u16, owner: Option<AccountId>) {
+            let owner = match owner {
+                Some(owner) => owner,
+                None => env.caller(),
+            };
+            assert!(!is_zero(&owner), "the contract owner cannot be the zero address");
+            self.name.set(name.clone());
+            self.symbol.set(symbol.clone());
             self.total_minted.set(0);
+            self.total_supply.set(0);
+            self.start_token_id.set(start_token_id);
+            self.next_token_id.set(start_token_id);
+            self.max_supply.set(max_supply);
             // set ownership of contract
-            self.owner.set(env.caller());
-            // mint initial tokens
+            self.owner.set(owner);
+            self.emit_per_token_events.set(true);
+            self.paused.set(false);
+            self.pause_expiry.set(0);
+            self.pause_reason.set(Vec::new());
+            self.holder_count.set(0);
+            self.transfer_fee.set(0);
+            self.royalty_recipient.set(owner);
+            // a floor above 100% could never be met
+            let royalty_floor_bps = royalty_floor_bps.min(10000);
+            self.royalty_floor_bps.set(royalty_floor_bps);
+            self.royalty_bps.set(royalty_floor_bps);
+            self.enforce_allowlist.set(false);
+            self.auto_operator.set(zero_account());
+            self.sale_phase.set(PHASE_CLOSED);
+            self.max_batch_size.set(DEFAULT_MAX_BATCH_SIZE);
+            self.enforce_receiver_optin.set(false);
+            self.total_active_approvals.set(0);
+            self.base_uri.set(Vec::new());
+            self.max_uri_len.set(DEFAULT_MAX_URI_LEN);
+            self.mint_price.set(0);
+            self.max_per_address.set(0);
+            self.mint_proceeds.set(0);
+            self.revealed.set(false);
+            self.payees.set(Vec::new());
+            self.deployer.set(env.caller());
+            self.minter_count.set(0);
+            self.max_mints_per_block.set(0);
+            self.block_mint_count.set((0, 0));
+            self.minting_enabled.set(true);
+            self.snapshot_id.set(0);
+            self.recovery.set(zero_account());
+            self.recovery_delay.set(0);
+            self.recovery_started_at.set(0);
+            self.transfer_cooldown.set(0);
+            self.metadata_frozen.set(false);
+            self.restrict_to_operators.set(false);
+            self.reveal_range_count.set(0);
+            self.consume_approval.set(false);
+            self.reapproval_cooldown.set(0);
+            self.total_revenue.set(0);
+            self.max_nesting_depth.set(DEFAULT_MAX_NESTING_DEPTH);
+            self.burning_enabled.set(burning_enabled);
+            self.voucher_signer.set([0x0; 32]);
+            // mint initial tokens, EventDeployed must not report a genesis mint that did not happen
             if init_value > 0 {
-                self.mint_impl(env.caller(), init_value);
+                assert!(self.mint_impl(owner, init_value, owner), "the initial tokens could not be minted");
             }
+
+            env.emit(EventDeployed {
+                owner: owner,
+                name: name,
+                symbol: symbol,
+                init_value: init_value,
+                max_supply: max_supply,
+            });
         }
     }
 
     /// Events
-    event EventMint { owner: AccountId, value: u64 }
+    event EventMint { owner: AccountId, value: u64, first_id: u64, last_id: u64 }
     event EventTransfer { from: AccountId, to: AccountId, token_id: u64 }
     event EventApproval { owner: AccountId, spender: AccountId, token_id: u64, approved: bool }
+    event EventApprovalForAll { owner: AccountId, operator: AccountId, approved: bool }
+    event EventOperationFailed { caller: AccountId, op_code: u8, token_id: u64 }
+    event EventDeployed { owner: AccountId, name: Vec<u8>, symbol: Vec<u8>, init_value: u64, max_supply: u64 }
+    event EventStaked { owner: AccountId, token_id: u64 }
+    event EventUnstaked { owner: AccountId, token_id: u64, duration: u64 }
+    event EventListed { token_id: u64, seller: AccountId, price: u64 }
+    event EventDelisted { token_id: u64 }
+    event EventSold { token_id: u64, seller: AccountId, buyer: AccountId, price: u64 }
+    event EventMetadataUpdate { token_id: u64 }
+    event EventBatchMetadataUpdate { from_token_id: u64, to_token_id: u64 }
+    event EventSnapshot { id: u64 }
+    event EventSale { token_id: u64, from: AccountId, to: AccountId, price: u64 }
+    event EventAuctionCreated { token_id: u64, seller: AccountId, reserve: u64, end_time: u64 }
+    event EventBid { token_id: u64, bidder: AccountId, amount: u64 }
+    event EventAuctionSettled { token_id: u64, winner: AccountId, amount: u64 }
+    event EventAttach { child_id: u64, parent_id: u64 }
+    event EventDetach { child_id: u64, parent_id: u64 }
+    event EventTip { token_id: u64, from: AccountId, creator: AccountId, amount: Balance }
+    event EventHashlocked { token_id: u64, to: AccountId, hashlock: [u8; 32], expires_at: u64 }
 
     /// Public methods
     impl NFToken {
 
         /// Returns whether an account is approved to send a token
         pub(external) fn is_approved(&self, token_id: u64, approved: AccountId) -> bool {
-            let approval = self.approvals.get(&token_id); // Borrowing &token_id reference
-            // AccountId returns option
-            if let None = approval {
-                return false;
+            self.is_approved_spender(token_id, &approved)
+        }
+
+        /// Return every token whose single-token approval is held by spender
+        pub(external) fn tokens_approved_to(&self, spender: AccountId) -> Vec<u64> {
+            let mut tokens = Vec::new();
+            for index in 0..*self.total_supply {
+                let token_id = *self.all_tokens.get(&index).unwrap();
+                if self.is_approved_spender(token_id, &spender) {
+                    tokens.push(token_id);
+                }
             }
-            if *approval.unwrap() == approved {
-                return true;
+            tokens
+        }
+
+        /// Returns whether an operator is approved to send all tokens of an owner
+        pub(external) fn is_approved_for_all(&self, owner: AccountId, operator: AccountId) -> bool {
+            self.is_operator(&owner, &operator)
+        }
+
+        /// Returns whether spender may send a token of owner, being the owner itself,
+        /// approved for the token or an operator of owner
+        ///
+        /// False whenever owner does not own the token.
+        pub(external) fn has_any_approval(&self, owner: AccountId, spender: AccountId, token_id: u64) -> bool {
+            if !self.is_token_owner(&owner, token_id) {
+                return false;
             }
-            false
+            spender == owner || self.is_approved_spender(token_id, &spender) || self.is_operator(&owner, &spender)
+        }
+
+        /// Returns, per operator, whether it is approved to send all tokens of an owner
+        pub(external) fn is_approved_for_all_batch(&self, owner: AccountId, operators: Vec<AccountId>) -> Vec<bool> {
+            operators
+                .iter()
+                .map(|operator| self.is_operator(&owner, operator))
+                .collect()
+        }
+
+        /// Returns the approved spender of a token (zero address if none) and
+        /// whether the token's owner has approved any operator
+        pub(external) fn approval_state(&self, token_id: u64) -> (AccountId, bool) {
+            let spender = self.approved_spender(token_id)
+                .unwrap_or(zero_account());
+
+            let has_operator = match self.token_owner(token_id) {
+                Some(owner) => *self.operator_count.get(&owner).unwrap_or(&0) > 0,
+                None => false,
+            };
+            (spender, has_operator)
+        }
+
+        /// Return the name of the collection
+        pub(external) fn name(&self) -> Vec<u8> {
+            (*self.name).clone()
+        }
+
+        /// Return the symbol of the collection
+        pub(external) fn symbol(&self) -> Vec<u8> {
+            (*self.symbol).clone()
+        }
+
+        /// Return the account id of this contract
+        pub(external) fn contract_account(&self) -> AccountId {
+            env.account_id()
         }
 
         /// Return the total amount of tokens ever minted
@@ -64,213 +543,5770 @@ contract! {
             total_minted
         }
 
+        /// Return the counters a dashboard monitors, as (total_minted, total_supply,
+        /// holder_count, total_active_approvals, total_revenue)
+        pub(external) fn stats(&self) -> (u64, u64, u64, u64, u64) {
+            (
+                *self.total_minted,
+                *self.total_supply,
+                *self.holder_count,
+                *self.total_active_approvals,
+                *self.total_revenue,
+            )
+        }
+
+        /// Return the owner of a token, the zero address if it does not exist
+        pub(external) fn owner_of(&self, token_id: u64) -> AccountId {
+            let owner = self.token_owner(token_id).unwrap_or(zero_account());
+            owner
+        }
+
+        /// Return the id given to the first minted token
+        pub(external) fn start_token_id(&self) -> u64 {
+            let start_token_id = *self.start_token_id;
+            start_token_id
+        }
+
+        /// Return the amount of tokens currently in existence
+        pub(external) fn total_supply(&self) -> u64 {
+            let total_supply = *self.total_supply;
+            total_supply
+        }
+
+        /// Return the ids of the last n tokens added to the enumeration, newest first
+        ///
+        /// These are the most recent mints as long as nothing was burned: a
+        /// burn moves the last token into the freed slot.
+        pub(external) fn recent_mints(&self, n: u64) -> Vec<u64> {
+            let total_supply = *self.total_supply;
+            let start = total_supply.saturating_sub(n);
+            (start..total_supply)
+                .rev()
+                .map(|index| *self.all_tokens.get(&index).unwrap())
+                .collect()
+        }
+
+        /// Return the id of the live token at an enumeration index below total_supply, 0 if out of range
+        pub(external) fn token_by_index(&self, index: u64) -> u64 {
+            let token_id = *self.all_tokens.get(&index).unwrap_or(&0);
+            token_id
+        }
+
+        /// Return the amount of tokens ever minted to an account,
+        /// unaffected by later transfers and burns
+        pub(external) fn minted_to(&self, account: AccountId) -> u64 {
+            let minted_to = *self.minted_to.get(&account).unwrap_or(&0);
+            minted_to
+        }
+
+        /// Return (total_minted, total_supply, max_supply, remaining) in one call
+        ///
+        /// remaining is how many tokens can still be minted, or how many fit
+        /// in a u64 when the supply is uncapped.
+        pub(external) fn supply_stats(&self) -> (u64, u64, u64, u64) {
+            let remaining = if *self.max_supply == 0 {
+                u64::max_value() - *self.total_minted
+            } else {
+                *self.max_supply - *self.total_minted
+            };
+            (*self.total_minted, *self.total_supply, *self.max_supply, remaining)
+        }
+
+        /// Lowers the max supply, only the contract owner can
+        ///
+        /// The cap can never be raised again, nor lowered below the tokens
+        /// already minted. An uncapped supply can be capped this way.
+        pub(external) fn reduce_max_supply(&mut self, new_max: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            if new_max == 0 || new_max < *self.total_minted {
+                return false;
+            }
+            if *self.max_supply != 0 && new_max >= *self.max_supply {
+                return false;
+            }
+            self.max_supply.set(new_max);
+            true
+        }
+
+        /// Returns whether the max supply is set and has been fully minted
+        pub(external) fn is_sold_out(&self) -> bool {
+            *self.max_supply != 0 && *self.total_minted >= *self.max_supply
+        }
+
+        /// Returns, per token id, whether account owns it
+        pub(external) fn are_owned_by(&self, account: AccountId, token_ids: Vec<u64>) -> Vec<bool> {
+            token_ids
+                .iter()
+                .map(|token_id| self.is_token_owner(&account, *token_id))
+                .collect()
+        }
+
+        /// Returns whether an account holds at least one token of the collection
+        pub(external) fn holds_any(&self, account: AccountId) -> bool {
+            *self.owner_to_token_count.get(&account).unwrap_or(&0) > 0
+        }
+
+        /// Return the number of tokens in the enumeration of an owner's tokens
+        ///
+        /// Maintained apart from balance_of, so that the two can be cross-checked.
+        pub(external) fn owned_count(&self, owner: AccountId) -> u64 {
+            let count = *self.owned_tokens_count.get(&owner).unwrap_or(&0);
+            count
+        }
+
+        /// Return the token at an index of an owner's enumeration below owned_count, 0 if out of range
+        pub(external) fn token_of_owner_by_index(&self, owner: AccountId, index: u64) -> u64 {
+            let token_id = *self.owned_tokens.get(&(owner, index)).unwrap_or(&0);
+            token_id
+        }
+
         /// Return the balance of the given address
         pub(external) fn balance_of(&self, owner: AccountId) -> u64 {
             let balance = *self.owner_to_token_count.get(&owner).unwrap_or(&0);
             balance
         }
 
+        /// Return the balances of the given addresses, in the same order
+        pub(external) fn balances_of(&self, owners: Vec<AccountId>) -> Vec<u64> {
+            owners
+                .iter()
+                .map(|owner| *self.owner_to_token_count.get(owner).unwrap_or(&0))
+                .collect()
+        }
+
+        /// Return the timestamp of the last mint or transfer of a token, 0 if unminted
+        pub(external) fn last_transfer_at(&self, token_id: u64) -> u64 {
+            let last_transfer = *self.last_transfer.get(&token_id).unwrap_or(&0);
+            last_transfer
+        }
+
+        /// Checks whether from could transfer a token to to right now, and why not
+        pub(external) fn can_transfer(&self, from: AccountId, to: AccountId, token_id: u64) -> Result<(), Error> {
+            self.check_transfer(&from, &to, token_id)
+        }
+
+        /// Returns whether account could move a token right now
+        ///
+        /// Unlike is_approved and can_transfer this also accounts for the
+        /// pause, freezes, locks, stakes and the transfer cooldown. Checks
+        /// that depend on the recipient are left out.
+        pub(external) fn can_transfer_now(&self, account: AccountId, token_id: u64) -> bool {
+            let owner = self.token_owner(token_id);
+            if let None = owner {
+                return false;
+            }
+            let owner = owner.unwrap();
+
+            let quantity = *self.quantity_approvals.get(&(owner, account)).unwrap_or(&0);
+            if owner != account && !self.is_approved_spender(token_id, &account)
+                && !self.is_operator(&owner, &account) && quantity == 0 {
+                return false;
+            }
+            if *self.restrict_to_operators && owner == account {
+                return false;
+            }
+            self.token_movable_by(token_id, &account) && !self.cooling_down(token_id)
+        }
+
+        /// Checks whether value new tokens could be minted right now, and why not
+        pub(external) fn can_mint(&self, value: u64) -> Result<(), Error> {
+            self.check_mint(value)
+        }
+
         /// Transfers a token_id to a specified address from the caller
         pub(external) fn transfer(&mut self, to: AccountId, token_id: u64) -> bool {
-            // carry out the actual transfer
-            if self.transfer_impl(env.caller(), to, token_id) == true {
-                env.emit(EventTransfer { from: env.caller(), to: to, token_id: token_id });
-                return true;
+            self.paid_transfer(to, token_id, false)
+        }
+
+        /// Transfers a token of the caller like transfer, tipping its creator
+        ///
+        /// Whatever is sent along beyond the transfer fee is credited to the
+        /// creator of the token. A zero tip is a plain transfer.
+        pub(external) fn transfer_with_tip(&mut self, to: AccountId, token_id: u64) -> bool {
+            self.paid_transfer(to, token_id, true)
+        }
+
+        /// Return the account that minted a token, the zero address if unknown
+        pub(external) fn creator_of(&self, token_id: u64) -> AccountId {
+            let creator = *self.creators.get(&token_id).unwrap_or(&zero_account());
+            creator
+        }
+
+        /// Transfers a token of the caller like transfer, recording the price it was sold for
+        ///
+        /// The price is informational for indexers, no funds change hands.
+        pub(external) fn transfer_with_price(&mut self, to: AccountId, token_id: u64, price: u64) -> bool {
+            let from = env.caller();
+            if !self.paid_transfer(to, token_id, false) {
+                return false;
             }
-            false
+            self.record_sale(token_id, from, to, price);
+            true
+        }
+
+        /// Return the price of the latest recorded sale of a token, 0 if none
+        pub(external) fn last_sale_of(&self, token_id: u64) -> u64 {
+            let price = *self.last_sale_price.get(&token_id).unwrap_or(&0);
+            price
         }
 
         /// Transfers a token_id from a specified address to another specified address
         pub(external) fn transfer_from(&mut self, to: AccountId, token_id: u64) -> bool {
-            // make the transfer immediately if caller is the owner
-            if self.is_token_owner(&env.caller(), token_id) { // &env.caller() gives a reference
-                let result = self.transfer_impl(env.caller(), to, token_id);
-                if result == true {
-                    env.emit(EventTransfer { from: env.caller(), to: to, token_id: token_id });
-                }
-                return result;
-
-            // not owner: check if caller is approved to move the token
-            } else {
-                let approval = self.approvals.get(&token_id);
-                if let None = approval {
-                    return false;
-                }
+            // the transfer fee has to be sent along with the call
+            let paid = self.value_transferred();
+            if !self.covers_transfer_fee(paid, 1) {
+                self.credit(env.caller(), paid);
+                return self.operation_failed(OpCode::TransferFrom, token_id);
+            }
 
-                // carry out transfer if caller is approved
-                if *approval.unwrap() == env.caller() {
-                    // carry out the actual transfer
-                    let result = self.transfer_impl(env.caller(), to, token_id);
-                    if result == true {
-                        env.emit(EventTransfer { from: env.caller(), to: to, token_id: token_id });
-                    }
-                    return result;
-                } else {
-                    return false;
-                }
+            if self.transfer_from_impl(to, token_id) == true {
+                self.charge_transfer_fee(paid, 1);
+                return true;
             }
+            // refund the fee of a failed transfer
+            self.credit(env.caller(), paid);
+            false
         }
         
         /// Mints a specified amount of new tokens to a given address
         pub(external) fn mint(&mut self, to: AccountId, value: u64) -> bool {
-            if env.caller() != *self.owner {
-                return false;
+            let caller = env.caller();
+            if !self.may_mint(&caller) || !self.within_mint_quota(&caller, value) {
+                return self.operation_failed(OpCode::Mint, 0);
             }
 
             // carry out the actual minting
-            if self.mint_impl(to, value) == true {
-                env.emit(EventMint { owner: to, value: value });
+            if self.mint_impl(to, value, caller) == true {
+                self.use_mint_quota(caller, value);
                 return true;
             }
-            false
+            self.operation_failed(OpCode::Mint, 0)
         }
 
-        /// Approves or disapproves an Account to send token on behalf of an owner
-        pub(external) fn approval(&mut self, to: AccountId, token_id: u64, approved: bool) -> bool {
-            // return if caller is not the token owner
-            let token_owner = self.id_to_owner.get(&token_id);
-            if let None = token_owner {
-                return false;
+        /// Mints amounts[i] new tokens to recipients[i] for every entry
+        ///
+        /// The whole batch is rejected before anything is minted if the
+        /// amounts overflow or exceed the max supply.
+        pub(external) fn batch_mint(&mut self, recipients: Vec<AccountId>, amounts: Vec<u64>) -> bool {
+            if !self.may_mint(&env.caller()) {
+                return self.operation_failed(OpCode::BatchMint, 0);
             }
-
-            let token_owner = *token_owner.unwrap();
-            if token_owner != env.caller() {
-                return false;
+            if recipients.len() != amounts.len() {
+                return self.operation_failed(OpCode::BatchMint, 0);
+            }
+            if recipients.len() as u64 > *self.max_batch_size {
+                return self.operation_failed(OpCode::BatchMint, 0);
             }
 
-            let approvals = self.approvals.get(&token_id);
-
-            // insert approval if
-            if let None = approvals {
-                if approved == true {
-                    self.approvals.insert(token_id, to);
-                } else {
-                    return false;
+            // sum up the batch without overflowing
+            let mut batch_total: u64 = 0;
+            for amount in amounts.iter() {
+                match batch_total.checked_add(*amount) {
+                    Some(total) => batch_total = total,
+                    None => return self.operation_failed(OpCode::BatchMint, 0),
                 }
+            }
+            if self.check_mint(batch_total).is_err() {
+                return self.operation_failed(OpCode::BatchMint, 0);
+            }
+            let caller = env.caller();
+            if !self.within_mint_quota(&caller, batch_total) {
+                return self.operation_failed(OpCode::BatchMint, 0);
+            }
 
-            } else {
-                let existing = *approvals.unwrap();
+            for (to, value) in recipients.iter().zip(amounts.iter()) {
+                self.mint_impl(*to, *value, caller);
+            }
+            self.use_mint_quota(caller, batch_total);
+            true
+        }
 
-                // remove existing owner if disapproving
-                // disapprove is possible
-                if existing == to && approved == false {
-                    self.approvals.remove(&token_id);
-                }
+        /// Mints every token id in the inclusive range from_id..=to_id to a given address
+        ///
+        /// Meant for migrations: the whole range is rejected if any id in it
+        /// already exists or was burned. Minters are limited like with mint.
+        pub(external) fn mint_range(&mut self, to: AccountId, from_id: u64, to_id: u64) -> bool {
+            let caller = env.caller();
+            if from_id > to_id {
+                return false;
+            }
+            let count = (to_id - from_id).checked_add(1);
+            if let None = count {
+                return false;
+            }
+            let count = count.unwrap();
+            if !self.may_mint(&caller) || !self.within_mint_quota(&caller, count) {
+                return false;
+            }
+            if self.check_mint(count).is_err() {
+                return false;
+            }
 
-                // overwrite or insert if approving is true
-                if approved == true {
-                    self.approvals.insert(token_id, to);
+            // reject the whole range if any id is taken, burned ids are never minted again
+            for token_id in from_id..=to_id {
+                if self.token_data.get(&token_id).is_some() || self.burned.get(&token_id).is_some() {
+                    return false;
                 }
             }
 
-            env.emit(EventApproval { owner: env.caller(), spender: to, token_id: token_id, approved: approved });
+            for token_id in from_id..=to_id {
+                self.mint_token(to, token_id, caller);
+            }
+            self.finish_mint(to, count, from_id, to_id);
+            self.use_mint_quota(caller, count);
             true
         }
-    }
 
+        /// Approves or disapproves an Account to send token on behalf of an owner
+        pub(external) fn approval(&mut self, to: AccountId, token_id: u64, approved: bool) -> bool {
+            self.approval_impl(to, token_id, approved, 0)
+        }
 
-    /// Private methods
-    impl NFToken {
+        /// Approves an Account to send token on behalf of an owner until a timestamp
+        pub(external) fn approve_until(&mut self, to: AccountId, token_id: u64, until: u64) -> bool {
+            self.approval_impl(to, token_id, true, until)
+        }
 
-        /// 
-        fn is_token_owner(&self, of: &AccountId, token_id: u64) -> bool {
-            let owner = self.id_to_owner.get(&token_id);
-            if let None = owner {
+        /// Approves or disapproves several operators to send all tokens of the caller
+        pub(external) fn set_approval_for_all_batch(&mut self, operators: Vec<AccountId>, approved: bool) -> bool {
+            if operators.len() as u64 > *self.max_batch_size {
                 return false;
             }
-            let owner = *owner.unwrap();
-            if owner != *of {
+            if operators.iter().any(|operator| *operator == env.caller()) {
                 return false;
             }
+
+            for operator in operators.iter() {
+                self.set_operator(env.caller(), *operator, approved);
+                env.emit(EventApprovalForAll { owner: env.caller(), operator: *operator, approved: approved });
+            }
             true
         }
 
-        /// Transfers token from a specified address to another address
-        fn transfer_impl(&mut self, from: AccountId, to: AccountId, token_id: u64) -> bool {
-            if !self.is_token_owner(&from, token_id) {
+        /// Approves or disapproves an operator to send all tokens of the caller
+        pub(external) fn set_approval_for_all(&mut self, operator: AccountId, approved: bool) -> bool {
+            if operator == env.caller() {
                 return false;
             }
 
-            self.id_to_owner.insert(token_id, to);
-
-            // update owner token counts
-            let from_owner_count = *self.owner_to_token_count.get(&from).unwrap_or(&0);
-            let to_owner_count = *self.owner_to_token_count.get(&to).unwrap_or(&0);
-
-            self.owner_to_token_count.insert(from, from_owner_count - 1);
-            self.owner_to_token_count.insert(to, to_owner_count + 1);
+            self.set_operator(env.caller(), operator, approved);
+            env.emit(EventApprovalForAll { owner: env.caller(), operator: operator, approved: approved });
             true
         }
 
-        /// minting of new tokens implementation
-        fn mint_impl(&mut self, receiver: AccountId, value: u64) -> bool {
+        /// Terminates the contract and sends the storage deposit back to the owner
+        ///
+        /// This is irreversible: every token, balance and approval is removed
+        /// with the contract. Only the contract owner can call it.
+        pub(external) fn terminate(&mut self) {
+            if env.caller() != *self.owner {
+                return;
+            }
+            env.terminate_contract(*self.owner);
+        }
 
-            let start_id = *self.total_minted + 1;
-            let stop_id = *self.total_minted + value;
+        /// Returns whether transfers and mints are currently paused
+        pub(external) fn is_paused(&self) -> bool {
+            self.paused_now()
+        }
 
-            // loop through new tokens being minted
-            for token_id in start_id..stop_id {
-                self.id_to_owner.insert(token_id, receiver);
+        /// Pauses transfers and mints until unpaused or until expiry passes
+        ///
+        /// An expiry of 0 keeps the contract paused until `unpause` is called.
+        /// The reason is kept for anyone to read until then.
+        pub(external) fn pause(&mut self, expiry: u64, reason: Vec<u8>) -> bool {
+            if env.caller() != *self.owner {
+                return false;
             }
+            self.paused.set(true);
+            self.pause_expiry.set(expiry);
+            self.pause_reason.set(reason);
+            true
+        }
 
-            // update total supply of owner
-            let from_owner_count = *self.owner_to_token_count.get(&self.owner).unwrap_or(&0);
-            self.owner_to_token_count.insert(*self.owner, from_owner_count + value);
+        /// Return why the contract was paused, empty when not paused
+        pub(external) fn pause_reason(&self) -> Vec<u8> {
+            (*self.pause_reason).clone()
+        }
 
-            // update total supply
-            self.total_minted += value;
+        /// Lifts the pause
+        pub(external) fn unpause(&mut self) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.paused.set(false);
+            self.pause_expiry.set(0);
+            self.pause_reason.set(Vec::new());
             true
         }
 
-    }
-}
-
-#[cfg(all(test, feature = "test-env"))]
-mod tests {
-    use super::*;
-    use std::convert::TryFrom;
+        /// Extends, shortens or clears (with 0) the expiry of the current pause
+        pub(external) fn set_pause_expiry(&mut self, expiry: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.pause_expiry.set(expiry);
+            true
+        }
 
-    #[test]
-    fn it_works() {
+        /// Return the number of accounts holding at least one token
+        pub(external) fn holder_count(&self) -> u64 {
+            let holder_count = *self.holder_count;
+            holder_count
+        }
 
-        // deploying and miting initial tokens
-        let mut _nftoken = NFToken::deploy_mock(100);
-        let alice = AccountId::try_from([0x0; 32]).unwrap();
-        let bob = AccountId::try_from([0x1; 32]).unwrap();
-        let charlie = AccountId::try_from([0x2; 32]).unwrap();
-        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        /// Return up to limit (holder, balance) pairs starting at holder index start
+        pub(external) fn holders_snapshot(&self, start: u64, limit: u64) -> Vec<(AccountId, u64)> {
+            let end = start.saturating_add(limit).min(*self.holder_count);
+            let mut snapshot = Vec::new();
+            for index in start..end {
+                let holder = *self.holders.get(&index).unwrap();
+                let balance = *self.owner_to_token_count.get(&holder).unwrap_or(&0);
+                snapshot.push((holder, balance));
+            }
+            snapshot
+        }
 
-        let total_minted = _nftoken.total_minted();
-        assert_eq!(total_minted, 100);
+        /// Return the native token fee required with every transfer
+        pub(external) fn transfer_fee(&self) -> u64 {
+            let transfer_fee = *self.transfer_fee;
+            transfer_fee
+        }
 
-        // transferring token_id from alice to bob
-        _nftoken.transfer(bob, 1);
+        /// Sets the native token fee required with every transfer
+        pub(external) fn set_transfer_fee(&mut self, fee: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.transfer_fee.set(fee);
+            true
+        }
 
-        let alice_balance = _nftoken.balance_of(alice);
-        let mut bob_balance = _nftoken.balance_of(bob);
+        /// Return the receiver of transfer fees and royalties
+        pub(external) fn royalty_recipient(&self) -> AccountId {
+            let royalty_recipient = *self.royalty_recipient;
+            royalty_recipient
+        }
 
-        assert_eq!(alice_balance, 99);
-        assert_eq!(bob_balance, 1);
+        /// Sets the receiver of transfer fees and royalties
+        pub(external) fn set_royalty_recipient(&mut self, recipient: AccountId) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.royalty_recipient.set(recipient);
+            true
+        }
 
-        // approve charlie to send token_id 2 from alice's account
-        _nftoken.approval(charlie, 2, true);
-        assert_eq!(_nftoken.is_approved(2, charlie), true);
+        /// Return the default royalty of newly minted tokens, in basis points
+        pub(external) fn royalty_bps(&self) -> u16 {
+            let royalty_bps = *self.royalty_bps;
+            royalty_bps
+        }
 
-        // overwrite charlie's approval with dave's approval
+        /// Sets the default royalty of newly minted tokens, in basis points (at most 10000)
+        ///
+        /// Tokens keep the royalty they were minted with, so a new default
+        /// only applies to later mints.
+        pub(external) fn set_royalty(&mut self, bps: u16) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            if bps > 10000 || bps < *self.royalty_floor_bps {
+                return false;
+            }
+            self.royalty_bps.set(bps);
+            true
+        }
+
+        /// Return the lowest royalty that can be set, in basis points
+        pub(external) fn royalty_floor_bps(&self) -> u16 {
+            let royalty_floor_bps = *self.royalty_floor_bps;
+            royalty_floor_bps
+        }
+
+        /// Return the royalty taken from sales of a token, in basis points
+        pub(external) fn royalty_of(&self, token_id: u64) -> u16 {
+            let royalty = *self.token_royalties.get(&token_id).unwrap_or(&0);
+            royalty
+        }
+
+        /// Overrides the royalty of a single minted token, in basis points (at most 10000)
+        pub(external) fn set_token_royalty(&mut self, token_id: u64, bps: u16) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            if self.token_data.get(&token_id).is_none() {
+                return false;
+            }
+            if bps > 10000 || bps < *self.royalty_floor_bps {
+                return false;
+            }
+            self.token_royalties.insert(token_id, bps);
+            true
+        }
+
+        /// Return the native tokens an account can withdraw
+        pub(external) fn payments_of(&self, account: AccountId) -> Balance {
+            let payments = *self.pending_payments.get(&account).unwrap_or(&0);
+            payments
+        }
+
+        /// Sends the caller all native tokens credited to them
+        pub(external) fn withdraw_payments(&mut self) -> bool {
+            let amount = self.pending_payments.remove(&env.caller()).unwrap_or(0);
+            if amount == 0 {
+                return false;
+            }
+            env.transfer(env.caller(), amount);
+            true
+        }
+
+        /// Returns whether an account is on the recipient allowlist
+        pub(external) fn is_recipient_allowed(&self, account: AccountId) -> bool {
+            *self.recipient_allowed.get(&account).unwrap_or(&false)
+        }
+
+        /// Adds or removes an account from the recipient allowlist
+        pub(external) fn set_recipient_allowed(&mut self, account: AccountId, allowed: bool) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            if allowed == true {
+                self.recipient_allowed.insert(account, true);
+            } else {
+                self.recipient_allowed.remove(&account);
+            }
+            true
+        }
+
+        /// Turns the restriction of transfers to allowed recipients on or off
+        pub(external) fn set_enforce_allowlist(&mut self, enforce: bool) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.enforce_allowlist.set(enforce);
+            true
+        }
+
+        /// Mints a single token for an external 32 byte reference and returns its id
+        ///
+        /// Returns None if the caller is not the owner, the reference was already
+        /// used or the token could not be minted.
+        pub(external) fn mint_with_external_id(&mut self, to: AccountId, external_ref: [u8; 32]) -> Option<u64> {
+            if env.caller() != *self.owner {
+                return None;
+            }
+            if self.ref_to_id.get(&external_ref).is_some() {
+                return None;
+            }
+            if self.mint_impl(to, 1, env.caller()) == false {
+                return None;
+            }
+
+            let token_id = *self.next_token_id - 1;
+            self.ref_to_id.insert(external_ref, token_id);
+            self.id_to_ref.insert(token_id, external_ref);
+            Some(token_id)
+        }
+
+        /// Return the token id minted for an external reference, None if none
+        pub(external) fn id_of_ref(&self, external_ref: [u8; 32]) -> Option<u64> {
+            let token_id = self.ref_to_id.get(&external_ref).cloned();
+            token_id
+        }
+
+        /// Return the external reference of a token, all zeroes if none
+        pub(external) fn ref_of_id(&self, token_id: u64) -> [u8; 32] {
+            let external_ref = *self.id_to_ref.get(&token_id).unwrap_or(&[0x0; 32]);
+            external_ref
+        }
+
+        /// Return the ed25519 public key vouchers are checked against, all zeroes if none
+        pub(external) fn voucher_signer(&self) -> [u8; 32] {
+            let voucher_signer = *self.voucher_signer;
+            voucher_signer
+        }
+
+        /// Sets the key that signs mint vouchers, only the contract owner can
+        ///
+        /// This has to be an ed25519 public key, kept apart from the owner
+        /// account: Substrate accounts are sr25519 keys by default, which
+        /// cannot be checked here. All zeroes turns voucher minting off.
+        pub(external) fn set_voucher_signer(&mut self, signer: [u8; 32]) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.voucher_signer.set(signer);
+            true
+        }
+
+        /// Mints token_id with uri to an address, authorized off-chain by the voucher signer
+        ///
+        /// signature is the voucher signer's ed25519 signature of the keccak256
+        /// hash of (contract account, to, token_id, uri) SCALE encoded. Anyone
+        /// can present it, but every voucher can only be redeemed once.
+        pub(external) fn redeem_voucher(&mut self, to: AccountId, token_id: u64, uri: Vec<u8>, signature: Vec<u8>) -> bool {
+            let voucher = keccak256(&(env.account_id(), to, token_id, uri.clone()).encode());
+            if self.used_vouchers.get(&voucher).is_some() {
+                return false;
+            }
+            if !self.signed_by_voucher_signer(&voucher, &signature) {
+                return false;
+            }
+            if self.check_mint(1).is_err() || *self.metadata_frozen {
+                return false;
+            }
+            if self.token_data.get(&token_id).is_some() || self.burned.get(&token_id).is_some() {
+                return false;
+            }
+            if uri.len() > *self.max_uri_len as usize {
+                return false;
+            }
+
+            self.used_vouchers.insert(voucher, true);
+            let owner = *self.owner;
+            self.mint_token(to, token_id, owner);
+            self.token_uris.insert(token_id, uri);
+            self.finish_mint(to, 1, token_id, token_id);
+            true
+        }
+
+        /// Returns whether a voucher has already been redeemed
+        pub(external) fn is_voucher_used(&self, to: AccountId, token_id: u64, uri: Vec<u8>) -> bool {
+            let voucher = keccak256(&(env.account_id(), to, token_id, uri).encode());
+            self.used_vouchers.get(&voucher).is_some()
+        }
+
+        /// Return the operator approved for every receiver of minted tokens
+        pub(external) fn auto_operator(&self) -> AccountId {
+            let auto_operator = *self.auto_operator;
+            auto_operator
+        }
+
+        /// Sets the operator approved for every receiver of minted tokens,
+        /// the zero address disables it
+        pub(external) fn set_auto_operator(&mut self, operator: AccountId) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.auto_operator.set(operator);
+            true
+        }
+
+        /// Return the metadata URI of a token, empty if none
+        ///
+        /// A token without its own URI resolves to the base URI of the revealed
+        /// range it falls in, or else the collection base URI, followed by its
+        /// id, or by its shuffled metadata index once revealed.
+        pub(external) fn token_uri(&self, token_id: u64) -> Vec<u8> {
+            self.uri_of(token_id)
+        }
+
+        /// Return the on-chain attributes of a token as (key, value) pairs, in the order they were added
+        pub(external) fn attributes_of(&self, token_id: u64) -> Vec<(Vec<u8>, Vec<u8>)> {
+            let count = *self.attribute_count.get(&token_id).unwrap_or(&0);
+            (0..count)
+                .map(|index| self.token_attributes.get(&(token_id, index)).unwrap().clone())
+                .collect()
+        }
+
+        /// Sets an on-chain attribute of an existing token, only the contract owner can
+        ///
+        /// Setting a key again replaces its value. Keys and values are
+        /// bound by the URI length limit, the number of attributes by the
+        /// batch size limit.
+        pub(external) fn set_attribute(&mut self, token_id: u64, key: Vec<u8>, value: Vec<u8>) -> bool {
+            if env.caller() != *self.owner || *self.metadata_frozen {
+                return false;
+            }
+            if self.token_data.get(&token_id).is_none() {
+                return false;
+            }
+            let max_len = *self.max_uri_len as usize;
+            if key.is_empty() || key.len() > max_len || value.len() > max_len {
+                return false;
+            }
+
+            let count = *self.attribute_count.get(&token_id).unwrap_or(&0);
+            let existing = (0..count)
+                .find(|index| self.token_attributes.get(&(token_id, *index)).unwrap().0 == key);
+            let index = match existing {
+                Some(index) => index,
+                None => {
+                    if count >= *self.max_batch_size {
+                        return false;
+                    }
+                    self.attribute_count.insert(token_id, count + 1);
+                    count
+                }
+            };
+            self.token_attributes.insert((token_id, index), (key, value));
+            true
+        }
+
+        /// Return the metadata of a token as a JSON document, empty if it does not exist
+        ///
+        /// Holds its name (the collection name and its id), its URI and its
+        /// attributes as trait_type/value pairs.
+        pub(external) fn token_metadata_json(&self, token_id: u64) -> Vec<u8> {
+            if self.token_data.get(&token_id).is_none() {
+                return Vec::new();
+            }
+            let mut name = (*self.name).clone();
+            name.extend_from_slice(b" #");
+            append_decimal(&mut name, token_id);
+
+            let mut json = Vec::new();
+            json.extend_from_slice(b"{\"name\":");
+            append_json_string(&mut json, &name);
+            json.extend_from_slice(b",\"uri\":");
+            append_json_string(&mut json, &self.uri_of(token_id));
+            json.extend_from_slice(b",\"attributes\":[");
+            let count = *self.attribute_count.get(&token_id).unwrap_or(&0);
+            for index in 0..count {
+                let (key, value) = self.token_attributes.get(&(token_id, index)).unwrap();
+                if index > 0 {
+                    json.push(b',');
+                }
+                json.extend_from_slice(b"{\"trait_type\":");
+                append_json_string(&mut json, key);
+                json.extend_from_slice(b",\"value\":");
+                append_json_string(&mut json, value);
+                json.push(b'}');
+            }
+            json.extend_from_slice(b"]}");
+            json
+        }
+
+        /// Reveals the token ids from_id..=to_id under their own base URI, only the contract owner can
+        ///
+        /// Ranges cannot overlap earlier revealed ranges.
+        pub(external) fn reveal_range(&mut self, from_id: u64, to_id: u64, base_uri: Vec<u8>) -> bool {
+            if env.caller() != *self.owner || *self.metadata_frozen {
+                return false;
+            }
+            if from_id > to_id || base_uri.is_empty() || base_uri.len() > *self.max_uri_len as usize {
+                return false;
+            }
+            for index in 0..*self.reveal_range_count {
+                let (first, last) = *self.reveal_ranges.get(&index).unwrap();
+                if from_id <= last && first <= to_id {
+                    return false;
+                }
+            }
+            let index = *self.reveal_range_count;
+            self.reveal_ranges.insert(index, (from_id, to_id));
+            self.reveal_range_uris.insert(index, base_uri);
+            self.reveal_range_count += 1;
+            true
+        }
+
+        /// Return the URI prefix of tokens without their own URI
+        pub(external) fn base_uri(&self) -> Vec<u8> {
+            (*self.base_uri).clone()
+        }
+
+        /// Sets the URI prefix of tokens without their own URI
+        pub(external) fn set_base_uri(&mut self, uri: Vec<u8>) -> bool {
+            if env.caller() != *self.owner || *self.metadata_frozen {
+                return false;
+            }
+            if uri.len() > *self.max_uri_len as usize {
+                return false;
+            }
+            self.base_uri.set(uri);
+            true
+        }
+
+        /// Return the longest URI accepted, in bytes
+        pub(external) fn max_uri_len(&self) -> u32 {
+            let max_uri_len = *self.max_uri_len;
+            max_uri_len
+        }
+
+        /// Sets the longest URI accepted, in bytes
+        pub(external) fn set_max_uri_len(&mut self, len: u32) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.max_uri_len.set(len);
+            true
+        }
+
+        /// Return the hash of a token's off-chain metadata, all zeroes if none
+        pub(external) fn content_hash_of(&self, token_id: u64) -> [u8; 32] {
+            let content_hash = *self.token_content_hash.get(&token_id).unwrap_or(&[0x0; 32]);
+            content_hash
+        }
+
+        /// Sets the metadata URI of an existing token, clearing its content hash
+        pub(external) fn set_token_uri(&mut self, token_id: u64, uri: Vec<u8>) -> bool {
+            if env.caller() != *self.owner || *self.metadata_frozen {
+                return false;
+            }
+            if self.token_data.get(&token_id).is_none() {
+                return false;
+            }
+            if uri.len() > *self.max_uri_len as usize {
+                return false;
+            }
+            // a content hash does not describe a new URI
+            self.token_uris.insert(token_id, uri);
+            self.token_content_hash.remove(&token_id);
+            true
+        }
+
+        /// Sets the metadata URIs of many existing tokens, atomically
+        ///
+        /// token_ids and uris pair up by position. Nothing is set unless
+        /// every token exists and every URI fits the URI length limit.
+        /// Content hashes set for the tokens are cleared.
+        pub(external) fn set_token_uris(&mut self, token_ids: Vec<u64>, uris: Vec<Vec<u8>>) -> bool {
+            if env.caller() != *self.owner || *self.metadata_frozen {
+                return false;
+            }
+            if token_ids.len() != uris.len() || token_ids.len() as u64 > *self.max_batch_size {
+                return false;
+            }
+            for (token_id, uri) in token_ids.iter().zip(uris.iter()) {
+                if self.token_data.get(token_id).is_none() || uri.len() > *self.max_uri_len as usize {
+                    return false;
+                }
+            }
+
+            for (token_id, uri) in token_ids.into_iter().zip(uris.into_iter()) {
+                self.token_uris.insert(token_id, uri);
+                self.token_content_hash.remove(&token_id);
+            }
+            true
+        }
+
+        /// Sets the metadata URI of an existing token together with the hash of its content
+        pub(external) fn set_token_uri_with_hash(&mut self, token_id: u64, uri: Vec<u8>, content_hash: [u8; 32]) -> bool {
+            if env.caller() != *self.owner || *self.metadata_frozen {
+                return false;
+            }
+            if self.token_data.get(&token_id).is_none() {
+                return false;
+            }
+            if uri.len() > *self.max_uri_len as usize {
+                return false;
+            }
+            self.token_uris.insert(token_id, uri);
+            self.token_content_hash.insert(token_id, content_hash);
+            true
+        }
+
+        /// Atomically swaps token_a of the caller with token_b of counterparty
+        ///
+        /// The counterparty must have approved the caller (or this contract)
+        /// to move token_b, either for the token or as an operator. The caller
+        /// pays the transfer fee for both tokens.
+        pub(external) fn swap(&mut self, token_a: u64, counterparty: AccountId, token_b: u64) -> bool {
+            let paid = self.value_transferred();
+            if !self.covers_transfer_fee(paid, 2) || !self.swap_impl(token_a, counterparty, token_b) {
+                self.credit(env.caller(), paid);
+                return false;
+            }
+            self.charge_transfer_fee(paid, 2);
+            true
+        }
+
+        /// Return the current sale phase: 0 = closed, 1 = allowlist, 2 = public
+        pub(external) fn sale_phase(&self) -> u8 {
+            let sale_phase = *self.sale_phase;
+            sale_phase
+        }
+
+        /// Moves the sale to another phase
+        pub(external) fn set_sale_phase(&mut self, phase: u8) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            if phase > PHASE_PUBLIC {
+                return false;
+            }
+            self.sale_phase.set(phase);
+            true
+        }
+
+        /// Adds or removes an account from the mint allowlist
+        pub(external) fn set_mint_allowlisted(&mut self, account: AccountId, allowed: bool) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            if allowed == true {
+                self.mint_allowlist.insert(account, true);
+            } else {
+                self.mint_allowlist.remove(&account);
+            }
+            true
+        }
+
+        /// Mints tokens to an allowlisted caller during the allowlist phase
+        pub(external) fn allowlist_mint(&mut self, value: u64) -> bool {
+            let allowed = *self.sale_phase == PHASE_ALLOWLIST
+                && *self.mint_allowlist.get(&env.caller()).unwrap_or(&false);
+            self.sale_mint(allowed, value)
+        }
+
+        /// Mints tokens to the caller during the public phase
+        pub(external) fn public_mint(&mut self, value: u64) -> bool {
+            let allowed = *self.sale_phase == PHASE_PUBLIC;
+            self.sale_mint(allowed, value)
+        }
+
+        /// Return (mint_price, max_per_address, max_supply, sale_phase) in one call
+        pub(external) fn sale_config(&self) -> (u64, u64, u64, u8) {
+            (*self.mint_price, *self.max_per_address, *self.max_supply, *self.sale_phase)
+        }
+
+        /// Sets the price per token of sale mints
+        pub(external) fn set_mint_price(&mut self, price: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.mint_price.set(price);
+            true
+        }
+
+        /// Return the payees of the mint proceeds and their shares in basis points
+        pub(external) fn payees(&self) -> Vec<(AccountId, u16)> {
+            let payees = (*self.payees).clone();
+            payees
+        }
+
+        /// Sets the payees the mint proceeds are split among, only the contract owner can
+        ///
+        /// The shares are in basis points and must sum to 10000, an empty list
+        /// pays everything to the contract owner.
+        pub(external) fn set_payees(&mut self, payees: Vec<(AccountId, u16)>) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            let total: u32 = payees.iter().map(|(_, shares)| *shares as u32).sum();
+            if !payees.is_empty() && total != 10000 {
+                return false;
+            }
+            if payees.iter().any(|(payee, _)| is_zero(payee)) {
+                return false;
+            }
+            self.payees.set(payees);
+            true
+        }
+
+        /// Splits the mint proceeds among the payees, only the contract owner can
+        ///
+        /// Each payee is credited their share, to be collected with
+        /// withdraw_payments. Rounding dust goes to the first payee.
+        pub(external) fn withdraw(&mut self) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            let proceeds = *self.mint_proceeds;
+            if proceeds == 0 {
+                return false;
+            }
+            self.mint_proceeds.set(0);
+
+            let payees = (*self.payees).clone();
+            if payees.is_empty() {
+                self.credit(*self.owner, Balance::from(proceeds));
+                return true;
+            }
+            let mut paid = 0;
+            for (payee, shares) in payees.iter().skip(1) {
+                let amount = (proceeds as u128 * *shares as u128 / 10000) as u64;
+                self.credit(*payee, Balance::from(amount));
+                paid += amount;
+            }
+            self.credit(payees[0].0, Balance::from(proceeds - paid));
+            true
+        }
+
+        /// Sets how many tokens an address may hold minted in total for
+        /// sale mints to succeed, 0 for no limit
+        pub(external) fn set_max_per_address(&mut self, max: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.max_per_address.set(max);
+            true
+        }
+
+        /// Return the operator holding a sale lock on a token, the zero address if unlocked
+        pub(external) fn locked_by(&self, token_id: u64) -> AccountId {
+            let locker = *self.locked_by.get(&token_id)
+                .unwrap_or(&zero_account());
+            locker
+        }
+
+        /// Locks a token while it is listed, so that only the caller can move it
+        ///
+        /// The caller must be approved for the token or be an operator of its owner.
+        /// Tokens under auction or escrowed behind a hashlock cannot be locked.
+        pub(external) fn lock_for_sale(&mut self, token_id: u64) -> bool {
+            let owner = self.token_owner(token_id);
+            if let None = owner {
+                return false;
+            }
+            let owner = owner.unwrap();
+
+            let caller = env.caller();
+            if !self.is_approved_spender(token_id, &caller) && !self.is_operator(&owner, &caller) {
+                return false;
+            }
+            if self.locked_by.get(&token_id).is_some() {
+                return false;
+            }
+            // a lock would keep the auction from settling or the escrow from being claimed
+            if self.auctions.get(&token_id).is_some() || self.hashlocks.get(&token_id).is_some() {
+                return false;
+            }
+            self.locked_by.insert(token_id, caller);
+            true
+        }
+
+        /// Releases a sale lock, only the operator holding it can do so
+        pub(external) fn unlock(&mut self, token_id: u64) -> bool {
+            let locker = self.locked_by.get(&token_id);
+            if let None = locker {
+                return false;
+            }
+            if *locker.unwrap() != env.caller() {
+                return false;
+            }
+            self.locked_by.remove(&token_id);
+            true
+        }
+
+        /// Turns the per-token mint Transfer events on or off
+        ///
+        /// When off, a mint only emits the aggregate EventMint with its id range.
+        pub(external) fn set_emit_per_token_events(&mut self, enabled: bool) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.emit_per_token_events.set(enabled);
+            true
+        }
+
+        /// Return when a token was staked, 0 if it is not staked
+        pub(external) fn staked_at(&self, token_id: u64) -> u64 {
+            let staked_at = *self.staked_at.get(&token_id).unwrap_or(&0);
+            staked_at
+        }
+
+        /// Stakes a token of the caller, it cannot be transferred until unstaked
+        ///
+        /// Only a token that could move right now can be staked, so staking
+        /// cannot hold up an auction, a sale lock or an escrow.
+        pub(external) fn stake(&mut self, token_id: u64) -> bool {
+            if !self.is_token_owner(&env.caller(), token_id) {
+                return false;
+            }
+            if !self.token_movable(token_id) {
+                return false;
+            }
+            self.staked_at.insert(token_id, env.now());
+            self.stakers.insert(token_id, env.caller());
+            env.emit(EventStaked { owner: env.caller(), token_id: token_id });
+            true
+        }
+
+        /// Unstakes a token of the caller and returns how long it was staked
+        ///
+        /// Returns 0 if the token is not staked or the caller is not the account that staked it.
+        pub(external) fn unstake(&mut self, token_id: u64) -> u64 {
+            if self.stakers.get(&token_id) != Some(&env.caller()) {
+                return 0;
+            }
+            self.stakers.remove(&token_id);
+            let staked_at = self.staked_at.remove(&token_id).unwrap();
+
+            let duration = env.now() - staked_at;
+            env.emit(EventUnstaked { owner: env.caller(), token_id: token_id, duration: duration });
+            duration
+        }
+
+        /// Returns whether tokens can be burned in this collection
+        pub(external) fn burning_enabled(&self) -> bool {
+            *self.burning_enabled
+        }
+
+        /// Destroys a token of the caller
+        pub(external) fn burn(&mut self, token_id: u64) -> bool {
+            if !*self.burning_enabled {
+                return false;
+            }
+            if !self.is_token_owner(&env.caller(), token_id) {
+                return false;
+            }
+            if !self.token_movable(token_id) || self.child_count.get(&token_id).is_some() {
+                return false;
+            }
+            self.burn_impl(env.caller(), token_id);
+            true
+        }
+
+        /// Destroys a token on behalf of its owner
+        ///
+        /// The caller must be approved for the token or be an operator of its owner.
+        pub(external) fn burn_from(&mut self, token_id: u64) -> bool {
+            if !*self.burning_enabled {
+                return false;
+            }
+            let owner = self.token_owner(token_id);
+            if let None = owner {
+                return false;
+            }
+            let owner = owner.unwrap();
+
+            let caller = env.caller();
+            if !self.is_approved_spender(token_id, &caller) && !self.is_operator(&owner, &caller) {
+                return false;
+            }
+            if !self.token_movable(token_id) || self.child_count.get(&token_id).is_some() {
+                return false;
+            }
+            self.burn_impl(owner, token_id);
+            true
+        }
+
+        /// Burns all listed tokens of the caller, atomically
+        ///
+        /// Each token logs a Transfer to the zero address, the caller's
+        /// balance is updated once.
+        pub(external) fn batch_burn(&mut self, token_ids: Vec<u64>) -> bool {
+            if !*self.burning_enabled {
+                return false;
+            }
+            if token_ids.len() as u64 > *self.max_batch_size {
+                return false;
+            }
+
+            // check every token before burning anything
+            let caller = env.caller();
+            for (index, token_id) in token_ids.iter().enumerate() {
+                if !self.is_token_owner(&caller, *token_id) || !self.token_movable(*token_id) {
+                    return false;
+                }
+                if self.child_count.get(token_id).is_some() {
+                    return false;
+                }
+                if token_ids[..index].contains(token_id) {
+                    return false;
+                }
+            }
+
+            for token_id in token_ids.iter() {
+                self.burn_token(caller, *token_id);
+            }
+            let owner_count = *self.owner_to_token_count.get(&caller).unwrap_or(&0);
+            self.set_balance(caller, owner_count - token_ids.len() as u64);
+            true
+        }
+
+        /// Return the number of tokens that currently have a single-token approval
+        pub(external) fn total_active_approvals(&self) -> u64 {
+            let total_active_approvals = *self.total_active_approvals;
+            total_active_approvals
+        }
+
+        /// Return the maximum number of entries accepted by batch calls
+        pub(external) fn max_batch_size(&self) -> u64 {
+            let max_batch_size = *self.max_batch_size;
+            max_batch_size
+        }
+
+        /// Sets the maximum number of entries accepted by batch calls
+        pub(external) fn set_max_batch_size(&mut self, size: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.max_batch_size.set(size);
+            true
+        }
+
+        /// Transfers each listed token of the caller to its (recipient, token_id) entry
+        ///
+        /// Atomic: nothing moves unless every token can be transferred.
+        /// EventTransfer is emitted once per entry, in input order.
+        pub(external) fn distribute(&mut self, transfers: Vec<(AccountId, u64)>) -> bool {
+            self.batch_transfer_impl(transfers)
+        }
+
+        /// Transfers the listed tokens of the caller to a single address
+        ///
+        /// Atomic like distribute, with one EventTransfer per token in the
+        /// order of token_ids.
+        pub(external) fn batch_transfer(&mut self, to: AccountId, token_ids: Vec<u64>) -> bool {
+            let transfers = token_ids.into_iter()
+                .map(|token_id| (to, token_id))
+                .collect();
+            self.batch_transfer_impl(transfers)
+        }
+
+        /// Returns whether an account opted in to receiving tokens
+        pub(external) fn can_receive(&self, account: AccountId) -> bool {
+            *self.can_receive.get(&account).unwrap_or(&false)
+        }
+
+        /// Opts the caller in to or out of receiving tokens
+        pub(external) fn set_can_receive(&mut self, can_receive: bool) -> bool {
+            if can_receive == true {
+                self.can_receive.insert(env.caller(), true);
+            } else {
+                self.can_receive.remove(&env.caller());
+            }
+            true
+        }
+
+        /// Turns the restriction of transfers to opted-in receivers on or off
+        pub(external) fn set_enforce_receiver_optin(&mut self, enforce: bool) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.enforce_receiver_optin.set(enforce);
+            true
+        }
+
+        /// Return the seller and price of a token's listing, (zero address, 0) if unlisted
+        ///
+        /// Expired listings read as unlisted, as do listings the contract is no
+        /// longer approved for.
+        pub(external) fn listing_of(&self, token_id: u64) -> (AccountId, u64) {
+            self.live_listing(token_id).unwrap_or((zero_account(), 0))
+        }
+
+        /// Lists a token of the caller for sale at a price
+        ///
+        /// The caller must have approved this contract for the token, either
+        /// for the token itself or as an operator.
+        pub(external) fn list(&mut self, token_id: u64, price: u64) -> bool {
+            self.list_impl(token_id, price, 0)
+        }
+
+        /// Lists a token of the caller for sale at a price until a timestamp
+        pub(external) fn list_until(&mut self, token_id: u64, price: u64, expires_at: u64) -> bool {
+            self.list_impl(token_id, price, expires_at)
+        }
+
+        /// Removes an expired listing, anyone can call it
+        pub(external) fn clear_expired_listing(&mut self, token_id: u64) -> bool {
+            let listing = self.listings.get(&token_id);
+            if let None = listing {
+                return false;
+            }
+            let (_, _, expires_at) = *listing.unwrap();
+            if expires_at == 0 || env.now() < expires_at {
+                return false;
+            }
+            self.listings.remove(&token_id);
+            env.emit(EventDelisted { token_id: token_id });
+            true
+        }
+
+        /// Removes a listing of the caller
+        pub(external) fn delist(&mut self, token_id: u64) -> bool {
+            let listing = self.listings.get(&token_id);
+            if let None = listing {
+                return false;
+            }
+            let (seller, _, _) = *listing.unwrap();
+            if seller != env.caller() {
+                return false;
+            }
+            self.listings.remove(&token_id);
+            env.emit(EventDelisted { token_id: token_id });
+            true
+        }
+
+        /// Buys a listed token, the price has to be sent along with the call
+        ///
+        /// The seller is credited the price minus the royalty, which goes to
+        /// the royalty recipient. The transfer fee is due on top of the price,
+        /// anything paid above both is refundable.
+        pub(external) fn buy(&mut self, token_id: u64) -> bool {
+            let buyer = env.caller();
+            let paid = self.value_transferred();
+
+            let listing = self.live_listing(token_id);
+            if let None = listing {
+                self.credit(buyer, paid);
+                return false;
+            }
+            let (seller, price) = listing.unwrap();
+            let fee = *self.transfer_fee;
+            let due = price.checked_add(fee);
+            if due.map_or(true, |due| paid < Balance::from(due)) || self.check_transfer(&seller, &buyer, token_id).is_err() {
+                self.credit(buyer, paid);
+                return false;
+            }
+
+            self.move_token(seller, buyer, token_id);
+            env.emit(EventTransfer { from: seller, to: buyer, token_id: token_id });
+
+            self.pay_out_sale(token_id, seller, price);
+            self.collect_transfer_fee(fee);
+            self.credit(buyer, paid - Balance::from(price + fee));
+            self.record_sale(token_id, seller, buyer, price);
+            env.emit(EventSold { token_id: token_id, seller: seller, buyer: buyer, price: price });
+            true
+        }
+
+        /// Return the shuffled metadata index of a token, 0 before the reveal
+        pub(external) fn reveal_index_of(&self, token_id: u64) -> u64 {
+            let index = *self.reveal_index.get(&token_id).unwrap_or(&0);
+            index
+        }
+
+        /// Assigns every live token a shuffled metadata index, once
+        ///
+        /// The shuffle is a deterministic permutation of 1..=total_supply
+        /// derived from seed, so a seed committed to before the sale keeps
+        /// the reveal verifiable. An all-zero seed falls back to weak_seed.
+        pub(external) fn reveal_shuffle(&mut self, seed: [u8; 32]) -> bool {
+            if env.caller() != *self.owner || *self.metadata_frozen {
+                return false;
+            }
+            if *self.revealed {
+                return false;
+            }
+
+            let seed = if seed == [0; 32] { self.weak_seed() } else { seed };
+            let indices = shuffled_indices(*self.total_supply, &seed);
+            for (position, index) in indices.iter().enumerate() {
+                let token_id = *self.all_tokens.get(&(position as u64)).unwrap();
+                self.reveal_index.insert(token_id, *index);
+            }
+            self.revealed.set(true);
+            true
+        }
+
+        /// Tells indexers the metadata of a token changed, following EIP-4906
+        ///
+        /// The token owner or the contract owner can signal it.
+        pub(external) fn emit_metadata_update(&mut self, token_id: u64) -> bool {
+            let owner = self.token_owner(token_id);
+            if let None = owner {
+                return false;
+            }
+            let caller = env.caller();
+            if owner.unwrap() != caller && caller != *self.owner {
+                return false;
+            }
+            env.emit(EventMetadataUpdate { token_id: token_id });
+            true
+        }
+
+        /// Tells indexers the metadata of the token ids from..=to changed, only the contract owner can
+        pub(external) fn emit_metadata_update_range(&mut self, from: u64, to: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            if from > to {
+                return false;
+            }
+            env.emit(EventBatchMetadataUpdate {
+                from_token_id: from,
+                to_token_id: to,
+            });
+            true
+        }
+
+        /// Clears every single-token, operator and quantity approval the caller has granted
+        ///
+        /// Meant as a safety switch after a suspected key compromise.
+        pub(external) fn revoke_all_my_approvals(&mut self) {
+            let caller = env.caller();
+
+            let token_count = *self.owned_tokens_count.get(&caller).unwrap_or(&0);
+            for index in 0..token_count {
+                let token_id = *self.owned_tokens.get(&(caller, index)).unwrap();
+                let spender = self.approved_spender(token_id);
+                // expired approvals are dropped too, only live ones are logged
+                self.clear_approval(token_id);
+                if let Some(spender) = spender {
+                    env.emit(EventApproval { owner: caller, spender: spender, token_id: token_id, approved: false });
+                }
+            }
+
+            // removing swaps the last operator in, so always take the last one
+            let mut operator_count = *self.operator_count.get(&caller).unwrap_or(&0);
+            while operator_count > 0 {
+                let operator = *self.operators_of.get(&(caller, operator_count - 1)).unwrap();
+                self.set_operator(caller, operator, false);
+                env.emit(EventApprovalForAll { owner: caller, operator: operator, approved: false });
+                operator_count -= 1;
+            }
+
+            let mut spender_count = *self.quantity_spender_count.get(&caller).unwrap_or(&0);
+            while spender_count > 0 {
+                let spender = *self.quantity_spenders_of.get(&(caller, spender_count - 1)).unwrap();
+                self.set_quantity_approval(caller, spender, 0);
+                spender_count -= 1;
+            }
+        }
+
+        /// Return the current contract owner
+        pub(external) fn contract_owner(&self) -> AccountId {
+            let owner = *self.owner;
+            owner
+        }
+
+        /// Returns whether account is the current owner of the contract
+        pub(external) fn is_contract_owner(&self, account: AccountId) -> bool {
+            account == *self.owner
+        }
+
+        /// Return the account that deployed the contract, unaffected by ownership transfers
+        pub(external) fn deployer(&self) -> AccountId {
+            let deployer = *self.deployer;
+            deployer
+        }
+
+        /// Return the recovery account, its delay and when the pending recovery started (0 if none)
+        pub(external) fn recovery(&self) -> (AccountId, u64, u64) {
+            (*self.recovery, *self.recovery_delay, *self.recovery_started_at)
+        }
+
+        /// Designates an account that can take over the ownership delay after initiating it,
+        /// only the contract owner can
+        ///
+        /// The zero address disables recovery. Any pending recovery is cancelled,
+        /// so an owner that is still around can always stop a takeover.
+        pub(external) fn set_recovery(&mut self, recovery: AccountId, delay: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.recovery.set(recovery);
+            self.recovery_delay.set(delay);
+            self.recovery_started_at.set(0);
+            true
+        }
+
+        /// Starts the recovery delay, only the recovery account can
+        pub(external) fn initiate_recovery(&mut self) -> bool {
+            if is_zero(&*self.recovery) || env.caller() != *self.recovery {
+                return false;
+            }
+            if *self.recovery_started_at != 0 {
+                return false;
+            }
+            self.recovery_started_at.set(env.now().max(1));
+            true
+        }
+
+        /// Makes the recovery account the contract owner once the delay passed since initiate_recovery
+        pub(external) fn recover_ownership(&mut self) -> bool {
+            if is_zero(&*self.recovery) || env.caller() != *self.recovery {
+                return false;
+            }
+            let started_at = *self.recovery_started_at;
+            if started_at == 0 || env.now() < started_at.saturating_add(*self.recovery_delay) {
+                return false;
+            }
+            self.owner.set(env.caller());
+            self.recovery.set(zero_account());
+            self.recovery_started_at.set(0);
+            true
+        }
+
+        /// Hands the contract ownership to another account, only the contract owner can
+        ///
+        /// The recovery account set by the previous owner, and any recovery it
+        /// started, no longer apply.
+        pub(external) fn transfer_ownership(&mut self, new_owner: AccountId) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            if is_zero(&new_owner) {
+                return false;
+            }
+            self.owner.set(new_owner);
+            self.recovery.set(zero_account());
+            self.recovery_started_at.set(0);
+            true
+        }
+
+        /// Return every enabled minter
+        pub(external) fn list_minters(&self) -> Vec<AccountId> {
+            let mut minters = Vec::new();
+            for index in 0..*self.minter_count {
+                minters.push(*self.minter_list.get(&index).unwrap());
+            }
+            minters
+        }
+
+        /// Returns whether an account may mint: the contract owner or an enabled minter
+        pub(external) fn is_minter(&self, account: AccountId) -> bool {
+            self.may_mint(&account)
+        }
+
+        /// Return how many more tokens an account may mint through its delegated quota
+        pub(external) fn mint_quota_of(&self, minter: AccountId) -> u64 {
+            let quota = *self.mint_quotas.get(&minter).unwrap_or(&0);
+            quota
+        }
+
+        /// Allows an account to mint up to quota tokens in total, only the contract owner can
+        ///
+        /// Every mint of the account uses up its quota, 0 revokes it. Enabled
+        /// minters are not limited by a quota.
+        pub(external) fn delegate_mint(&mut self, minter: AccountId, quota: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            if quota == 0 {
+                self.mint_quotas.remove(&minter);
+            } else {
+                self.mint_quotas.insert(minter, quota);
+            }
+            true
+        }
+
+        /// Allows an account to mint and batch mint, only the contract owner can
+        pub(external) fn grant_minter(&mut self, minter: AccountId) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            if self.minters.get(&minter).is_some() {
+                return false;
+            }
+            let index = *self.minter_count;
+            self.minters.insert(minter, index);
+            self.minter_list.insert(index, minter);
+            self.minter_count += 1;
+            true
+        }
+
+        /// Takes the minting rights of an account away, only the contract owner can
+        pub(external) fn revoke_minter(&mut self, minter: AccountId) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            let index = self.minters.remove(&minter);
+            if let None = index {
+                return false;
+            }
+            let index = index.unwrap();
+
+            // move the last minter into the freed slot
+            let last_index = *self.minter_count - 1;
+            if index != last_index {
+                let last_minter = *self.minter_list.get(&last_index).unwrap();
+                self.minter_list.insert(index, last_minter);
+                self.minters.insert(last_minter, index);
+            }
+            self.minter_list.remove(&last_index);
+            self.minter_count -= 1;
+            true
+        }
+
+        /// Return the most tokens that can be minted within a single block, 0 if unlimited
+        pub(external) fn max_mints_per_block(&self) -> u64 {
+            let max = *self.max_mints_per_block;
+            max
+        }
+
+        /// Caps the tokens minted within a single block, 0 for no cap, only the contract owner can
+        pub(external) fn set_max_mints_per_block(&mut self, max: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.max_mints_per_block.set(max);
+            true
+        }
+
+        /// Returns (exists, frozen, burned) of a token in one call
+        pub(external) fn token_flags(&self, token_id: u64) -> (bool, bool, bool) {
+            let exists = self.token_data.get(&token_id).is_some();
+            let frozen = self.frozen.get(&token_id).is_some();
+            let burned = *self.burned.get(&token_id).unwrap_or(&false);
+            (exists, frozen, burned)
+        }
+
+        /// Freezes an existing token so that it can be neither transferred nor burned, only the contract owner can
+        pub(external) fn freeze(&mut self, token_id: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            if self.token_data.get(&token_id).is_none() {
+                return false;
+            }
+            self.frozen.insert(token_id, true);
+            true
+        }
+
+        /// Lifts the freeze of a token, only the contract owner can
+        pub(external) fn unfreeze(&mut self, token_id: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.frozen.remove(&token_id).is_some()
+        }
+
+        /// Return how many more tokens of owner spender may move through a quantity approval
+        pub(external) fn quantity_approval(&self, owner: AccountId, spender: AccountId) -> u64 {
+            let quantity = *self.quantity_approvals.get(&(owner, spender)).unwrap_or(&0);
+            quantity
+        }
+
+        /// Allows spender to move up to quantity of the caller's tokens, whichever ids, with transfer_from
+        ///
+        /// Every such move uses up one, 0 revokes the approval.
+        pub(external) fn approve_quantity(&mut self, spender: AccountId, quantity: u64) -> bool {
+            let caller = env.caller();
+            if spender == caller {
+                return false;
+            }
+            self.set_quantity_approval(caller, spender, quantity);
+            true
+        }
+
+        /// Returns whether new tokens can currently be minted
+        pub(external) fn minting_enabled(&self) -> bool {
+            *self.minting_enabled
+        }
+
+        /// Turns minting on or off without pausing transfers, only the contract owner can
+        pub(external) fn set_minting_enabled(&mut self, enabled: bool) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.minting_enabled.set(enabled);
+            true
+        }
+
+        /// Records the current balances under a new snapshot id and returns it, only the contract owner can
+        ///
+        /// Balances are not copied: an account's balance at the snapshot is
+        /// kept the first time it changes afterwards. Returns 0 on failure.
+        pub(external) fn snapshot(&mut self) -> u64 {
+            if env.caller() != *self.owner {
+                return 0;
+            }
+            self.snapshot_id += 1;
+            let id = *self.snapshot_id;
+            env.emit(EventSnapshot { id: id });
+            id
+        }
+
+        /// Return the id of the latest snapshot, 0 if none was taken
+        pub(external) fn current_snapshot_id(&self) -> u64 {
+            let snapshot_id = *self.snapshot_id;
+            snapshot_id
+        }
+
+        /// Return the balance of an account at a past snapshot, 0 for unknown snapshot ids
+        pub(external) fn balance_of_at(&self, account: AccountId, snapshot_id: u64) -> u64 {
+            if snapshot_id == 0 || snapshot_id > *self.snapshot_id {
+                return 0;
+            }
+            // the first checkpoint taken at or after the snapshot holds the balance it had back then
+            let count = *self.checkpoint_count.get(&account).unwrap_or(&0);
+            let (mut low, mut high) = (0, count);
+            while low < high {
+                let mid = (low + high) / 2;
+                let (id, _) = *self.balance_checkpoints.get(&(account, mid)).unwrap();
+                if id < snapshot_id {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            if low == count {
+                // unchanged since the snapshot
+                return *self.owner_to_token_count.get(&account).unwrap_or(&0);
+            }
+            let (_, balance) = *self.balance_checkpoints.get(&(account, low)).unwrap();
+            balance
+        }
+
+        /// Escrows a token of the caller for to until the preimage of hashlock is revealed with claim
+        ///
+        /// The token stays with the caller and cannot move meanwhile. Once
+        /// expires_at has passed without a claim, refund releases it. Emits EventHashlocked.
+        pub(external) fn transfer_with_hashlock(&mut self, to: AccountId, token_id: u64, hashlock: [u8; 32], expires_at: u64) -> bool {
+            let caller = env.caller();
+            if self.check_transfer(&caller, &to, token_id).is_err() {
+                return false;
+            }
+            if expires_at <= env.now() {
+                return false;
+            }
+            self.hashlocks.insert(token_id, (caller, to, hashlock, expires_at));
+            env.emit(EventHashlocked { token_id: token_id, to: to, hashlock: hashlock, expires_at: expires_at });
+            true
+        }
+
+        /// Return the (owner, recipient, hashlock, expiry) of an escrowed token, the zero address as owner if none
+        pub(external) fn hashlock_of(&self, token_id: u64) -> (AccountId, AccountId, [u8; 32], u64) {
+            let hashlock = *self.hashlocks.get(&token_id)
+                .unwrap_or(&(zero_account(), zero_account(), [0; 32], 0));
+            hashlock
+        }
+
+        /// Moves an escrowed token to its recipient, if keccak256 of preimage matches its hashlock
+        /// and it has not expired
+        ///
+        /// The caller pays the transfer fee, it is refundable if the claim fails.
+        pub(external) fn claim(&mut self, token_id: u64, preimage: Vec<u8>) -> bool {
+            let paid = self.value_transferred();
+            if !self.covers_transfer_fee(paid, 1) || !self.claim_impl(token_id, preimage) {
+                self.credit(env.caller(), paid);
+                return false;
+            }
+            self.charge_transfer_fee(paid, 1);
+            true
+        }
+
+        /// Releases an escrowed token back to its owner once the hashlock expired unclaimed
+        pub(external) fn refund(&mut self, token_id: u64) -> bool {
+            let hashlock = self.hashlocks.get(&token_id);
+            if let None = hashlock {
+                return false;
+            }
+            let (_, _, _, expires_at) = *hashlock.unwrap();
+            if env.now() < expires_at {
+                return false;
+            }
+            self.hashlocks.remove(&token_id);
+            true
+        }
+
+        /// Return the time a token has to stay put after it was minted or moved
+        pub(external) fn transfer_cooldown(&self) -> u64 {
+            let cooldown = *self.transfer_cooldown;
+            cooldown
+        }
+
+        /// Sets the time a token has to stay put after it was minted or moved, 0 for none,
+        /// only the contract owner can
+        pub(external) fn set_transfer_cooldown(&mut self, cooldown: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.transfer_cooldown.set(cooldown);
+            true
+        }
+
+        /// Returns whether the metadata has been frozen for good
+        pub(external) fn is_metadata_frozen(&self) -> bool {
+            *self.metadata_frozen
+        }
+
+        /// Freezes token URIs, the base URI and the reveal for good, only the contract owner can
+        pub(external) fn freeze_metadata(&mut self) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.metadata_frozen.set(true);
+            true
+        }
+
+        /// Returns whether tokens can only be moved by approved spenders and operators
+        pub(external) fn restrict_to_operators(&self) -> bool {
+            *self.restrict_to_operators
+        }
+
+        /// Blocks or allows owners moving their own tokens directly, only the contract owner can
+        ///
+        /// While restricted, tokens only move through transfer_from or a
+        /// purchase, e.g. by an approved marketplace contract.
+        pub(external) fn set_restrict_to_operators(&mut self, restrict: bool) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.restrict_to_operators.set(restrict);
+            true
+        }
+
+        /// Return whether approvals are single-use and how long a used one blocks re-approval
+        pub(external) fn consume_approval(&self) -> (bool, u64) {
+            (*self.consume_approval, *self.reapproval_cooldown)
+        }
+
+        /// Makes approvals single-use or not, only the contract owner can
+        ///
+        /// A transfer_from always clears the approval it used. When enabled,
+        /// the owner also cannot approve the same spender for the same token
+        /// again until cooldown has passed, guarding against UIs replaying an
+        /// approval.
+        pub(external) fn set_consume_approval(&mut self, enabled: bool, cooldown: u64) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.consume_approval.set(enabled);
+            self.reapproval_cooldown.set(cooldown);
+            true
+        }
+
+        /// Return the native tokens ever earned by the collection, unaffected by withdrawals
+        pub(external) fn total_revenue(&self) -> u64 {
+            let total_revenue = *self.total_revenue;
+            total_revenue
+        }
+
+        /// Auctions a token of the caller until end_time, bids have to reach the reserve price
+        ///
+        /// The contract must be approved to move the token, which cannot move
+        /// until the auction is settled.
+        pub(external) fn create_auction(&mut self, token_id: u64, reserve: u64, end_time: u64) -> bool {
+            let seller = env.caller();
+            if !self.is_token_owner(&seller, token_id) || !self.token_movable(token_id) {
+                return false;
+            }
+            let contract = env.account_id();
+            if !self.is_approved_spender(token_id, &contract) && !self.is_operator(&seller, &contract) {
+                return false;
+            }
+            if end_time <= env.now() {
+                return false;
+            }
+            self.auctions.insert(token_id, (seller, reserve, end_time, zero_account(), 0));
+            env.emit(EventAuctionCreated { token_id: token_id, seller: seller, reserve: reserve, end_time: end_time });
+            true
+        }
+
+        /// Return the top bidder and bid of an auction, the zero address and 0 if none
+        pub(external) fn highest_bid(&self, token_id: u64) -> (AccountId, u64) {
+            match self.auctions.get(&token_id) {
+                Some((_, _, _, bidder, amount)) => (*bidder, *amount),
+                None => (zero_account(), 0),
+            }
+        }
+
+        /// Bids the native tokens sent along with the call on a running auction
+        ///
+        /// A bid has to reach the reserve and beat the top bid, whose bidder
+        /// is refunded. A rejected bid is refundable.
+        pub(external) fn bid(&mut self, token_id: u64) -> bool {
+            let bidder = env.caller();
+            let paid = self.value_transferred();
+
+            // bids are kept as u64 like prices, larger payments are refunded
+            let auction = self.auctions.get(&token_id);
+            let amount = u64::try_from(paid);
+            if auction.is_none() || amount.is_err() {
+                self.credit(bidder, paid);
+                return false;
+            }
+            let amount = amount.unwrap();
+            let (seller, reserve, end_time, top_bidder, top_bid) = *auction.unwrap();
+            if env.now() >= end_time || bidder == seller || amount < reserve || amount <= top_bid {
+                self.credit(bidder, paid);
+                return false;
+            }
+
+            self.credit(top_bidder, Balance::from(top_bid));
+            self.auctions.insert(token_id, (seller, reserve, end_time, bidder, amount));
+            env.emit(EventBid { token_id: token_id, bidder: bidder, amount: amount });
+            true
+        }
+
+        /// Ends an auction after its end time, anyone can
+        ///
+        /// The token goes to the top bidder and the bid to the seller, minus
+        /// the transfer fee and the royalty. Without bids the token is simply released.
+        /// If the token cannot move, the auction is cancelled instead: the top
+        /// bid becomes refundable, the token stays and false is returned.
+        pub(external) fn settle_auction(&mut self, token_id: u64) -> bool {
+            let auction = self.auctions.get(&token_id);
+            if let None = auction {
+                return false;
+            }
+            let (seller, _, end_time, winner, amount) = *auction.unwrap();
+            if env.now() < end_time {
+                return false;
+            }
+            // the auction escrows the token, so it has to go before the token can move
+            self.auctions.remove(&token_id);
+
+            if is_zero(&winner) {
+                env.emit(EventAuctionSettled { token_id: token_id, winner: winner, amount: 0 });
+                return true;
+            }
+            if !self.move_token(seller, winner, token_id) {
+                // keeping the auction could lock the bid up for good, as nothing ends it
+                self.credit(winner, Balance::from(amount));
+                return false;
+            }
+            env.emit(EventTransfer { from: seller, to: winner, token_id: token_id });
+            let fee = core::cmp::min(*self.transfer_fee, amount);
+            self.collect_transfer_fee(fee);
+            self.pay_out_sale(token_id, seller, amount - fee);
+            self.record_sale(token_id, seller, winner, amount);
+            env.emit(EventAuctionSettled { token_id: token_id, winner: winner, amount: amount });
+            true
+        }
+
+        /// Return the shareholders of a co-owned token, empty if it is not co-owned
+        pub(external) fn shares_of(&self, token_id: u64) -> Vec<(AccountId, u16)> {
+            match self.token_shares.get(&token_id) {
+                Some(shares) => shares.clone(),
+                None => Vec::new(),
+            }
+        }
+
+        /// Marks a token of the caller as co-owned by shareholders
+        ///
+        /// Shares are in basis points and have to sum to 10000. From then on
+        /// the token only moves once every shareholder agreed with
+        /// set_share_approval. The shares end with the next transfer. Like
+        /// with stake, the token has to be able to move right now.
+        pub(external) fn set_shares(&mut self, token_id: u64, shares: Vec<(AccountId, u16)>) -> bool {
+            if !self.is_token_owner(&env.caller(), token_id) || !self.token_movable(token_id) {
+                return false;
+            }
+            if self.token_shares.get(&token_id).is_some() {
+                return false;
+            }
+            if shares.len() as u64 > *self.max_batch_size {
+                return false;
+            }
+            let total: u32 = shares.iter().map(|(_, share)| *share as u32).sum();
+            if total != 10000 {
+                return false;
+            }
+            for (index, (holder, _)) in shares.iter().enumerate() {
+                if is_zero(holder) || shares[..index].iter().any(|(other, _)| other == holder) {
+                    return false;
+                }
+            }
+            self.token_shares.insert(token_id, shares);
+            true
+        }
+
+        /// Agrees to, or withdraws agreement to, the next transfer of a co-owned token
+        pub(external) fn set_share_approval(&mut self, token_id: u64, approved: bool) -> bool {
+            let caller = env.caller();
+            let holds_share = match self.token_shares.get(&token_id) {
+                Some(shares) => shares.iter().any(|(holder, _)| *holder == caller),
+                None => false,
+            };
+            if !holds_share {
+                return false;
+            }
+            if approved {
+                self.share_approvals.insert((token_id, caller), true);
+            } else {
+                self.share_approvals.remove(&(token_id, caller));
+            }
+            true
+        }
+
+        /// Return the owners a token has had, from its minter to its current owner
+        ///
+        /// Only the most recent MAX_HISTORY_LEN owners are kept. The history of
+        /// a burned token stays readable.
+        pub(external) fn history_of(&self, token_id: u64) -> Vec<AccountId> {
+            match self.token_history.get(&token_id) {
+                Some(history) => history.clone(),
+                None => Vec::new(),
+            }
+        }
+
+        /// Return the token a token is attached to, 0 if it is not attached
+        pub(external) fn parent_of(&self, token_id: u64) -> u64 {
+            *self.parent_of.get(&token_id).unwrap_or(&0)
+        }
+
+        /// Return the deepest level a token can be attached at
+        pub(external) fn max_nesting_depth(&self) -> u8 {
+            let max_nesting_depth = *self.max_nesting_depth;
+            max_nesting_depth
+        }
+
+        /// Sets the deepest level a token can be attached at
+        pub(external) fn set_max_nesting_depth(&mut self, depth: u8) -> bool {
+            if env.caller() != *self.owner {
+                return false;
+            }
+            self.max_nesting_depth.set(depth);
+            true
+        }
+
+        /// Attaches a token of the caller to another token of the caller
+        ///
+        /// The child stays with the caller and follows its parent: it cannot
+        /// be transferred directly until detached. The parent may itself be
+        /// attached, but not below the child. A parent with attached tokens
+        /// cannot be burned.
+        pub(external) fn attach(&mut self, child_id: u64, parent_id: u64) -> bool {
+            let caller = env.caller();
+            if child_id == parent_id {
+                return false;
+            }
+            let root = self.root_token(parent_id);
+            if !self.is_token_owner(&caller, child_id) || !self.is_token_owner(&caller, root) {
+                return false;
+            }
+            if !self.token_movable(child_id) {
+                return false;
+            }
+            // attaching a token below itself would create a cycle
+            let ancestors = self.ancestors(parent_id);
+            if ancestors.contains(&child_id) {
+                return false;
+            }
+            // the child lands one level below the parent, its own children further down
+            if ancestors.len() as u64 + self.subtree_height(child_id) > *self.max_nesting_depth as u64 {
+                return false;
+            }
+
+            self.parent_of.insert(child_id, parent_id);
+            self.add_child(parent_id, child_id);
+            env.emit(EventAttach { child_id: child_id, parent_id: parent_id });
+            true
+        }
+
+        /// Detaches a token of the caller from its parent, it can be transferred on its own again
+        ///
+        /// Not while the outermost parent cannot move, as that would take the
+        /// token out of a pending sale or escrow.
+        pub(external) fn detach(&mut self, child_id: u64) -> bool {
+            let caller = env.caller();
+            let parent_id = match self.parent_of.get(&child_id) {
+                Some(parent_id) => *parent_id,
+                None => return false,
+            };
+            if !self.is_token_owner(&caller, child_id) || !self.token_movable(self.root_token(parent_id)) {
+                return false;
+            }
+
+            self.parent_of.remove(&child_id);
+            self.remove_child(parent_id, child_id);
+            env.emit(EventDetach { child_id: child_id, parent_id: parent_id });
+            true
+        }
+    }
+
+
+    /// Private methods
+    impl NFToken {
+
+        /// Emits a failure event for op and returns false
+        fn operation_failed(&self, op: OpCode, token_id: u64) -> bool {
+            env.emit(EventOperationFailed { caller: env.caller(), op_code: op as u8, token_id: token_id });
+            false
+        }
+
+        /// Whether spender is the single approved address of a token
+        fn is_approved_spender(&self, token_id: u64, spender: &AccountId) -> bool {
+            let approval = self.approved_spender(token_id);
+            // AccountId returns option
+            if let None = approval {
+                return false;
+            }
+            if approval.unwrap() == *spender {
+                return true;
+            }
+            false
+        }
+
+        /// The single approved address of a token, expired approvals count as absent
+        fn approved_spender(&self, token_id: u64) -> Option<AccountId> {
+            let data = self.token_data.get(&token_id); // Borrowing &token_id reference
+            if let None = data {
+                return None;
+            }
+            let (_, spender, until) = *data.unwrap();
+            self.live_approval(spender, until)
+        }
+
+        /// The spender of a stored approval, unless there is none or it expired
+        fn live_approval(&self, spender: AccountId, until: u64) -> Option<AccountId> {
+            if is_zero(&spender) {
+                return None;
+            }
+            if until != 0 && env.now() >= until {
+                return None;
+            }
+            Some(spender)
+        }
+
+        /// The owner of a token, None if it does not exist
+        fn token_owner(&self, token_id: u64) -> Option<AccountId> {
+            self.token_data.get(&token_id).map(|data| data.0)
+        }
+
+        /// Records a new owner of a token, dropping any approval of the previous owner
+        ///
+        /// Returns the spender whose approval was dropped, if any.
+        fn set_token_owner(&mut self, token_id: u64, owner: AccountId) -> Option<AccountId> {
+            let previous = self.token_data.insert(token_id, (owner, zero_account(), 0));
+            let mut cleared = None;
+            if let Some((previous_owner, spender, _)) = previous {
+                if !is_zero(&spender) {
+                    self.total_active_approvals -= 1;
+                    cleared = Some(spender);
+                }
+                self.remove_owned_token(previous_owner, token_id);
+            }
+            self.add_owned_token(owner, token_id);
+            self.append_history(token_id, owner);
+            cleared
+        }
+
+        /// Records a new owner in the history of a token, dropping the oldest entry once full
+        fn append_history(&mut self, token_id: u64, owner: AccountId) {
+            let mut history = match self.token_history.get(&token_id) {
+                Some(history) => history.clone(),
+                None => Vec::new(),
+            };
+            if history.len() >= MAX_HISTORY_LEN {
+                history.remove(0);
+            }
+            history.push(owner);
+            self.token_history.insert(token_id, history);
+        }
+
+        /// Appends a token to the enumeration of its owner's tokens
+        fn add_owned_token(&mut self, owner: AccountId, token_id: u64) {
+            let index = *self.owned_tokens_count.get(&owner).unwrap_or(&0);
+            self.owned_tokens.insert((owner, index), token_id);
+            self.owned_tokens_index.insert(token_id, index);
+            self.owned_tokens_count.insert(owner, index + 1);
+        }
+
+        /// Removes a token from the enumeration of its owner's tokens, moving the last one into its slot
+        fn remove_owned_token(&mut self, owner: AccountId, token_id: u64) {
+            let index = self.owned_tokens_index.remove(&token_id).unwrap();
+            let last_index = *self.owned_tokens_count.get(&owner).unwrap() - 1;
+            if index != last_index {
+                let last_token = *self.owned_tokens.get(&(owner, last_index)).unwrap();
+                self.owned_tokens.insert((owner, index), last_token);
+                self.owned_tokens_index.insert(last_token, index);
+            }
+            self.owned_tokens.remove(&(owner, last_index));
+            self.owned_tokens_count.insert(owner, last_index);
+        }
+
+        /// Whether operator is approved to send all tokens of owner
+        fn is_operator(&self, owner: &AccountId, operator: &AccountId) -> bool {
+            *self.operator_approvals.get(&(*owner, *operator)).unwrap_or(&false)
+        }
+
+        /// 
+        fn is_token_owner(&self, of: &AccountId, token_id: u64) -> bool {
+            let owner = self.token_owner(token_id);
+            if let None = owner {
+                return false;
+            }
+            let owner = owner.unwrap();
+            if owner != *of {
+                return false;
+            }
+            true
+        }
+
+        /// Transfers token from a specified address to another address
+        fn transfer_impl(&mut self, from: AccountId, to: AccountId, token_id: u64) -> Result<(), Error> {
+            // check_transfer already ran every transfer check, move_token would repeat them
+            self.check_transfer(&from, &to, token_id)?;
+            self.reassign_token(from, to, token_id);
+            Ok(())
+        }
+
+        /// Checks that from owns an existing token that may currently be moved to to
+        fn check_transfer(&self, from: &AccountId, to: &AccountId, token_id: u64) -> Result<(), Error> {
+            let owner = self.token_owner(token_id);
+            if let None = owner {
+                return Err(Error::TokenNotFound);
+            }
+            if owner.unwrap() != *from {
+                return Err(Error::NotOwner);
+            }
+            if *from == *to {
+                return Err(Error::SelfTransfer);
+            }
+            if self.paused_now() {
+                return Err(Error::Paused);
+            }
+            if !self.transfer_allowed(from, token_id, to) {
+                return Err(Error::TransferNotAllowed);
+            }
+            Ok(())
+        }
+
+        /// Whether a token of from may currently be moved to to by the caller
+        fn transfer_allowed(&self, from: &AccountId, token_id: u64, to: &AccountId) -> bool {
+            if !self.token_movable(token_id) {
+                return false;
+            }
+            if *self.restrict_to_operators && env.caller() == *from {
+                return false;
+            }
+            if self.cooling_down(token_id) {
+                return false;
+            }
+            if *self.enforce_allowlist && !*self.recipient_allowed.get(to).unwrap_or(&false) {
+                return false;
+            }
+            if *self.enforce_receiver_optin && !*self.can_receive.get(to).unwrap_or(&false) {
+                return false;
+            }
+            true
+        }
+
+        /// Whether every shareholder of a co-owned token agreed to move it
+        fn shareholders_agree(&self, token_id: u64) -> bool {
+            match self.token_shares.get(&token_id) {
+                Some(shares) => shares.iter()
+                    .all(|(holder, _)| *self.share_approvals.get(&(token_id, *holder)).unwrap_or(&false)),
+                None => true,
+            }
+        }
+
+        /// Drops the shares of a co-owned token along with the agreements of its shareholders
+        fn clear_shares(&mut self, token_id: u64) {
+            if let Some(shares) = self.token_shares.remove(&token_id) {
+                for (holder, _) in shares.iter() {
+                    self.share_approvals.remove(&(token_id, *holder));
+                }
+            }
+        }
+
+        /// Whether a token was transferred too recently to move again
+        fn cooling_down(&self, token_id: u64) -> bool {
+            if *self.transfer_cooldown == 0 {
+                return false;
+            }
+            let last_transfer = *self.last_transfer.get(&token_id).unwrap_or(&0);
+            env.now() < last_transfer.saturating_add(*self.transfer_cooldown)
+        }
+
+        /// Whether a token may currently leave its owner (by transfer or burn) at the caller's request
+        fn token_movable(&self, token_id: u64) -> bool {
+            self.token_movable_by(token_id, &env.caller())
+        }
+
+        /// Whether a token may currently leave its owner at the request of actor
+        fn token_movable_by(&self, token_id: u64, actor: &AccountId) -> bool {
+            // an attached token only moves along with its parent
+            if self.parent_of.get(&token_id).is_some() {
+                return false;
+            }
+            self.subtree_movable_by(token_id, actor)
+        }
+
+        /// Whether neither a token nor any token attached below it is held in place
+        fn subtree_movable_by(&self, token_id: u64, actor: &AccountId) -> bool {
+            if self.paused_now() {
+                return false;
+            }
+            if self.staked_at.get(&token_id).is_some() {
+                return false;
+            }
+            if self.frozen.get(&token_id).is_some() {
+                return false;
+            }
+            if self.auctions.get(&token_id).is_some() {
+                return false;
+            }
+            if self.hashlocks.get(&token_id).is_some() {
+                return false;
+            }
+            if !self.shareholders_agree(token_id) {
+                return false;
+            }
+            // a locked token can only be moved by the operator holding the lock
+            if let Some(locker) = self.locked_by.get(&token_id) {
+                if *locker != *actor {
+                    return false;
+                }
+            }
+            let child_count = *self.child_count.get(&token_id).unwrap_or(&0);
+            (0..child_count).all(|index| {
+                let child_id = *self.children.get(&(token_id, index)).unwrap();
+                self.subtree_movable_by(child_id, actor)
+            })
+        }
+
+        /// Moves a token to another address, from must already be checked to be its owner
+        ///
+        /// Runs the transfer checks itself, callers that already ran check_transfer
+        /// use reassign_token instead.
+        fn move_token(&mut self, from: AccountId, to: AccountId, token_id: u64) -> bool {
+            if from == to {
+                return false;
+            }
+            if !self.transfer_allowed(&from, token_id, &to) {
+                return false;
+            }
+            self.reassign_token(from, to, token_id);
+            true
+        }
+
+        /// Records a new owner for a token without any transfer checks
+        fn reassign_token(&mut self, from: AccountId, to: AccountId, token_id: u64) {
+            // approvals, locks and listings do not carry over to the new owner
+            if let Some(spender) = self.set_token_owner(token_id, to) {
+                env.emit(EventApproval { owner: from, spender: spender, token_id: token_id, approved: false });
+            }
+            self.last_transfer.insert(token_id, env.now());
+            self.locked_by.remove(&token_id);
+            self.listings.remove(&token_id);
+            self.clear_shares(token_id);
+
+            // update owner token counts
+            let from_owner_count = *self.owner_to_token_count.get(&from).unwrap_or(&0);
+            self.set_balance(from, from_owner_count - 1);
+            let to_owner_count = *self.owner_to_token_count.get(&to).unwrap_or(&0);
+            self.set_balance(to, to_owner_count + 1);
+
+            // attached tokens go along, the caller logs the transfer of the token itself
+            let child_count = *self.child_count.get(&token_id).unwrap_or(&0);
+            for index in 0..child_count {
+                let child_id = *self.children.get(&(token_id, index)).unwrap();
+                self.reassign_token(from, to, child_id);
+                env.emit(EventTransfer { from: from, to: to, token_id: child_id });
+            }
+        }
+
+        /// Checks that the contract is not paused and value more tokens can be minted
+        fn check_mint(&self, value: u64) -> Result<(), Error> {
+            if self.paused_now() {
+                return Err(Error::Paused);
+            }
+            if !self.can_mint_amount(value) {
+                return Err(Error::MintNotAllowed);
+            }
+            Ok(())
+        }
+
+        /// Whether minting is enabled and value more tokens fit under the max supply
+        /// and the per-block cap without overflowing
+        fn can_mint_amount(&self, value: u64) -> bool {
+            if !*self.minting_enabled {
+                return false;
+            }
+            let new_total = self.total_minted.checked_add(value);
+            if let None = new_total {
+                return false;
+            }
+            if *self.max_supply != 0 && new_total.unwrap() > *self.max_supply {
+                return false;
+            }
+            if *self.max_mints_per_block != 0 && self.minted_this_block().saturating_add(value) > *self.max_mints_per_block {
+                return false;
+            }
+            true
+        }
+
+        /// Tokens minted so far in the current block
+        fn minted_this_block(&self) -> u64 {
+            let (block, count) = *self.block_mint_count;
+            if block != self.block_number() {
+                return 0;
+            }
+            count
+        }
+
+        /// Number of the current block
+        fn block_number(&self) -> u64 {
+            env.block_number() as u64
+        }
+
+        /// minting of new tokens implementation, creator is recorded as the creator of every token
+        fn mint_impl(&mut self, receiver: AccountId, value: u64, creator: AccountId) -> bool {
+            if self.check_mint(value).is_err() {
+                return false;
+            }
+
+            // loop through new tokens being minted, skipping ids already taken by mint_range
+            // or redeem_voucher, and ids burned since, which are never minted again
+            let mut token_id = *self.next_token_id;
+            let mut first_id = token_id;
+            for minted in 0..value {
+                while self.token_data.get(&token_id).is_some() || self.burned.get(&token_id).is_some() {
+                    token_id += 1;
+                }
+                if minted == 0 {
+                    first_id = token_id;
+                }
+                self.mint_token(receiver, token_id, creator);
+                token_id += 1;
+            }
+            self.next_token_id.set(token_id);
+
+            self.finish_mint(receiver, value, first_id, token_id.saturating_sub(1));
+            true
+        }
+
+        /// Records a single new token for receiver, made by creator
+        fn mint_token(&mut self, receiver: AccountId, token_id: u64, creator: AccountId) {
+            self.set_token_owner(token_id, receiver);
+            self.creators.insert(token_id, creator);
+            self.last_transfer.insert(token_id, env.now());
+            self.token_royalties.insert(token_id, *self.royalty_bps);
+
+            // append to the enumeration of live tokens
+            let index = *self.total_supply;
+            self.all_tokens.insert(index, token_id);
+            self.all_tokens_index.insert(token_id, index);
+            self.total_supply += 1;
+
+            if *self.emit_per_token_events {
+                let from = zero_account();
+                env.emit(EventTransfer { from: from, to: receiver, token_id: token_id });
+            }
+        }
+
+        /// Updates receiver and supply bookkeeping once value tokens with ids
+        /// first_id..=last_id were minted to receiver, and emits EventMint
+        fn finish_mint(&mut self, receiver: AccountId, value: u64, first_id: u64, last_id: u64) {
+            // approve the configured operator for the receiver
+            let auto_operator = *self.auto_operator;
+            if !is_zero(&auto_operator) && auto_operator != receiver {
+                if !self.is_operator(&receiver, &auto_operator) {
+                    self.set_operator(receiver, auto_operator, true);
+                    env.emit(EventApprovalForAll { owner: receiver, operator: auto_operator, approved: true });
+                }
+            }
+
+            // update token count of receiver
+            let receiver_count = *self.owner_to_token_count.get(&receiver).unwrap_or(&0);
+            self.set_balance(receiver, receiver_count + value);
+
+            // update total supply
+            self.total_minted += value;
+            let minted_this_block = self.minted_this_block();
+            self.block_mint_count.set((self.block_number(), minted_this_block + value));
+            let minted_to = *self.minted_to.get(&receiver).unwrap_or(&0);
+            self.minted_to.insert(receiver, minted_to + value);
+
+            env.emit(EventMint { owner: receiver, value: value, first_id: first_id, last_id: last_id });
+        }
+
+        /// Whether the contract is paused and the pause has not expired yet
+        fn paused_now(&self) -> bool {
+            if !*self.paused {
+                return false;
+            }
+            *self.pause_expiry == 0 || env.now() < *self.pause_expiry
+        }
+
+        /// Updates the token count of owner, keeping the holders index in sync
+        fn set_balance(&mut self, owner: AccountId, balance: u64) {
+            let previous = *self.owner_to_token_count.get(&owner).unwrap_or(&0);
+            self.checkpoint_balance(owner, previous);
+            self.owner_to_token_count.insert(owner, balance);
+
+            // new holder: append to the index
+            if previous == 0 && balance > 0 {
+                let index = *self.holder_count;
+                self.holders.insert(index, owner);
+                self.holder_index.insert(owner, index);
+                self.holder_count += 1;
+            }
+
+            // holder left: move the last holder into the freed slot
+            if previous > 0 && balance == 0 {
+                let index = *self.holder_index.get(&owner).unwrap();
+                let last_index = *self.holder_count - 1;
+                if index != last_index {
+                    let last_holder = *self.holders.get(&last_index).unwrap();
+                    self.holders.insert(index, last_holder);
+                    self.holder_index.insert(last_holder, index);
+                }
+                self.holders.remove(&last_index);
+                self.holder_index.remove(&owner);
+                self.holder_count -= 1;
+            }
+        }
+
+        /// Keeps the balance of owner at the latest snapshot before it changes for the first time since
+        fn checkpoint_balance(&mut self, owner: AccountId, balance: u64) {
+            let snapshot_id = *self.snapshot_id;
+            if snapshot_id == 0 {
+                return;
+            }
+            let count = *self.checkpoint_count.get(&owner).unwrap_or(&0);
+            if count > 0 {
+                let (last_id, _) = *self.balance_checkpoints.get(&(owner, count - 1)).unwrap();
+                if last_id == snapshot_id {
+                    return;
+                }
+            }
+            self.balance_checkpoints.insert((owner, count), (snapshot_id, balance));
+            self.checkpoint_count.insert(owner, count + 1);
+        }
+
+        /// Transfers a token_id from its owner to another address, on behalf of the owner
+        fn transfer_from_impl(&mut self, to: AccountId, token_id: u64) -> bool {
+            // look up the owner and approval once for both the authorization and the transfer
+            let data = self.token_data.get(&token_id);
+            if let None = data {
+                return self.operation_failed(OpCode::TransferFrom, token_id);
+            }
+            let (owner, spender, until) = *data.unwrap();
+
+            // caller must be the owner, approved for the token or an operator of its owner,
+            // otherwise a quantity approval is used up
+            let caller = env.caller();
+            let approved = self.live_approval(spender, until) == Some(caller);
+            let authorized = owner == caller || approved || self.is_operator(&owner, &caller);
+            let quantity = *self.quantity_approvals.get(&(owner, caller)).unwrap_or(&0);
+            if !authorized && quantity == 0 {
+                return self.operation_failed(OpCode::TransferFrom, token_id);
+            }
+
+            // carry out the actual transfer
+            if !self.move_token(owner, to, token_id) {
+                return self.operation_failed(OpCode::TransferFrom, token_id);
+            }
+            if !authorized {
+                self.set_quantity_approval(owner, caller, quantity - 1);
+            }
+            if approved && *self.consume_approval {
+                self.consumed_approvals.insert((owner, token_id, caller), env.now());
+            }
+            env.emit(EventTransfer { from: owner, to: to, token_id: token_id });
+            true
+        }
+
+        /// Stores how many more tokens of owner spender may move, removing the entry at 0
+        fn set_quantity_approval(&mut self, owner: AccountId, spender: AccountId, quantity: u64) {
+            let count = *self.quantity_spender_count.get(&owner).unwrap_or(&0);
+            if quantity == 0 {
+                if self.quantity_approvals.remove(&(owner, spender)).is_none() {
+                    return;
+                }
+
+                // move the last spender of owner into the freed slot
+                let index = self.quantity_spenders_index.remove(&(owner, spender)).unwrap();
+                let last_index = count - 1;
+                if index != last_index {
+                    let last_spender = *self.quantity_spenders_of.get(&(owner, last_index)).unwrap();
+                    self.quantity_spenders_of.insert((owner, index), last_spender);
+                    self.quantity_spenders_index.insert((owner, last_spender), index);
+                }
+                self.quantity_spenders_of.remove(&(owner, last_index));
+                self.quantity_spender_count.insert(owner, count - 1);
+            } else if self.quantity_approvals.insert((owner, spender), quantity).is_none() {
+                self.quantity_spenders_of.insert((owner, count), spender);
+                self.quantity_spenders_index.insert((owner, spender), count);
+                self.quantity_spender_count.insert(owner, count + 1);
+            }
+        }
+
+        /// Native tokens sent along with the current call
+        fn value_transferred(&self) -> Balance {
+            env.value_transferred()
+        }
+
+        /// Credits native tokens to an account, to be withdrawn with withdraw_payments
+        fn credit(&mut self, account: AccountId, amount: Balance) {
+            if amount == 0 {
+                return;
+            }
+            let payments = *self.pending_payments.get(&account).unwrap_or(&0);
+            self.pending_payments.insert(account, payments + amount);
+        }
+
+        /// Records or removes an operator approval, keeping the operator count in sync
+        fn set_operator(&mut self, owner: AccountId, operator: AccountId, approved: bool) {
+            let was_approved = self.is_operator(&owner, &operator);
+            let count = *self.operator_count.get(&owner).unwrap_or(&0);
+
+            if approved == true && was_approved == false {
+                self.operator_approvals.insert((owner, operator), true);
+                self.operators_of.insert((owner, count), operator);
+                self.operators_index.insert((owner, operator), count);
+                self.operator_count.insert(owner, count + 1);
+            } else if approved == false && was_approved == true {
+                self.operator_approvals.remove(&(owner, operator));
+
+                // move the last operator of owner into the freed slot
+                let index = self.operators_index.remove(&(owner, operator)).unwrap();
+                let last_index = count - 1;
+                if index != last_index {
+                    let last_operator = *self.operators_of.get(&(owner, last_index)).unwrap();
+                    self.operators_of.insert((owner, index), last_operator);
+                    self.operators_index.insert((owner, last_operator), index);
+                }
+                self.operators_of.remove(&(owner, last_index));
+                self.operator_count.insert(owner, count - 1);
+            }
+        }
+
+        /// Approves or disapproves an Account to send token until a timestamp (0 = no expiry)
+        fn approval_impl(&mut self, to: AccountId, token_id: u64, approved: bool, until: u64) -> bool {
+            // return if caller is not the token owner
+            let data = self.token_data.get(&token_id);
+            if let None = data {
+                return self.operation_failed(OpCode::Approval, token_id);
+            }
+
+            let (token_owner, existing, _) = *data.unwrap();
+            if token_owner != env.caller() {
+                return self.operation_failed(OpCode::Approval, token_id);
+            }
+
+            // a used single-use approval cannot be granted again right away
+            if approved && *self.consume_approval {
+                if let Some(used_at) = self.consumed_approvals.get(&(token_owner, token_id, to)) {
+                    if env.now() < used_at.saturating_add(*self.reapproval_cooldown) {
+                        return self.operation_failed(OpCode::Approval, token_id);
+                    }
+                }
+            }
+
+            // insert approval if
+            if is_zero(&existing) {
+                if approved == true {
+                    self.store_approval(token_id, to, until);
+                } else {
+                    return self.operation_failed(OpCode::Approval, token_id);
+                }
+
+            } else {
+                // remove existing owner if disapproving
+                // disapprove is possible
+                if existing == to && approved == false {
+                    self.clear_approval(token_id);
+                }
+
+                // overwrite or insert if approving is true
+                if approved == true {
+                    self.store_approval(token_id, to, until);
+                }
+            }
+
+            env.emit(EventApproval { owner: env.caller(), spender: to, token_id: token_id, approved: approved });
+            true
+        }
+
+        /// Removes a token of owner from existence
+        fn burn_impl(&mut self, owner: AccountId, token_id: u64) {
+            self.burn_token(owner, token_id);
+            let owner_count = *self.owner_to_token_count.get(&owner).unwrap_or(&0);
+            self.set_balance(owner, owner_count - 1);
+        }
+
+        /// Removes a token of owner from existence, leaving the balance of owner to the caller
+        fn burn_token(&mut self, owner: AccountId, token_id: u64) {
+            let data = self.token_data.remove(&token_id);
+            if let Some((_, spender, _)) = data {
+                if !is_zero(&spender) {
+                    self.total_active_approvals -= 1;
+                }
+            }
+            self.remove_owned_token(owner, token_id);
+            self.frozen.remove(&token_id);
+            self.token_royalties.remove(&token_id);
+            self.burned.insert(token_id, true);
+            self.locked_by.remove(&token_id);
+            self.listings.remove(&token_id);
+            self.clear_shares(token_id);
+
+            // the metadata goes with the token
+            self.token_uris.remove(&token_id);
+            self.token_content_hash.remove(&token_id);
+            let attribute_count = self.attribute_count.remove(&token_id).unwrap_or(0);
+            for index in 0..attribute_count {
+                self.token_attributes.remove(&(token_id, index));
+            }
+
+            // move the last live token into the freed enumeration slot
+            let index = self.all_tokens_index.remove(&token_id).unwrap();
+            let last_index = *self.total_supply - 1;
+            if index != last_index {
+                let last_token = *self.all_tokens.get(&last_index).unwrap();
+                self.all_tokens.insert(index, last_token);
+                self.all_tokens_index.insert(last_token, index);
+            }
+            self.all_tokens.remove(&last_index);
+            self.total_supply -= 1;
+
+            let to = zero_account();
+            env.emit(EventTransfer { from: owner, to: to, token_id: token_id });
+        }
+
+        /// Stores the single-token approval of a token, keeping the approvals counter in sync
+        fn store_approval(&mut self, token_id: u64, spender: AccountId, until: u64) {
+            let data = self.token_data.get(&token_id);
+            if let None = data {
+                return;
+            }
+            let (owner, previous, _) = *data.unwrap();
+            // clearing a token with nothing approved would only cost a storage write
+            if is_zero(&previous) && is_zero(&spender) {
+                return;
+            }
+            if is_zero(&previous) && !is_zero(&spender) {
+                self.total_active_approvals += 1;
+            }
+            if !is_zero(&previous) && is_zero(&spender) {
+                self.total_active_approvals -= 1;
+            }
+            self.token_data.insert(token_id, (owner, spender, until));
+        }
+
+        /// Removes the single-token approval of a token, keeping the approvals counter in sync
+        fn clear_approval(&mut self, token_id: u64) {
+            self.store_approval(token_id, zero_account(), 0);
+        }
+
+        /// Lists a token of the caller, the contract must be approved to move it
+        fn list_impl(&mut self, token_id: u64, price: u64, expires_at: u64) -> bool {
+            let seller = env.caller();
+            if !self.is_token_owner(&seller, token_id) {
+                return false;
+            }
+            let contract = env.account_id();
+            if !self.is_approved_spender(token_id, &contract) && !self.is_operator(&seller, &contract) {
+                return false;
+            }
+            self.listings.insert(token_id, (seller, price, expires_at));
+            env.emit(EventListed { token_id: token_id, seller: seller, price: price });
+            true
+        }
+
+        /// The seller and price of a token's listing
+        ///
+        /// Expired listings count as absent, as do listings the seller revoked
+        /// the contract's approval for since.
+        fn live_listing(&self, token_id: u64) -> Option<(AccountId, u64)> {
+            let listing = self.listings.get(&token_id);
+            if let None = listing {
+                return None;
+            }
+            let (seller, price, expires_at) = *listing.unwrap();
+            if expires_at != 0 && env.now() >= expires_at {
+                return None;
+            }
+            let contract = env.account_id();
+            if !self.is_approved_spender(token_id, &contract) && !self.is_operator(&seller, &contract) {
+                return None;
+            }
+            Some((seller, price))
+        }
+
+        /// Mints value tokens to the caller during a sale, if allowed and paid for
+        ///
+        /// The payment of a rejected mint is refundable, as is anything paid
+        /// above the price.
+        fn sale_mint(&mut self, allowed: bool, value: u64) -> bool {
+            let buyer = env.caller();
+            let paid = self.value_transferred();
+
+            let price = self.mint_price.checked_mul(value);
+            if !allowed || price.is_none() || paid < Balance::from(price.unwrap()) {
+                self.credit(buyer, paid);
+                return false;
+            }
+            let price = price.unwrap();
+
+            let minted = *self.minted_to.get(&buyer).unwrap_or(&0);
+            if *self.max_per_address != 0 && minted.saturating_add(value) > *self.max_per_address {
+                self.credit(buyer, paid);
+                return false;
+            }
+            if self.mint_impl(buyer, value, buyer) == false {
+                self.credit(buyer, paid);
+                return false;
+            }
+
+            self.mint_proceeds += price;
+            self.total_revenue += price;
+            self.credit(buyer, paid - Balance::from(price));
+            true
+        }
+
+        /// Moves every (recipient, token_id) entry from the caller, charging the transfer fee per entry
+        fn batch_transfer_impl(&mut self, transfers: Vec<(AccountId, u64)>) -> bool {
+            let paid = self.value_transferred();
+            let moves = transfers.len() as u64;
+            if !self.covers_transfer_fee(paid, moves) || !self.batch_move(transfers) {
+                self.credit(env.caller(), paid);
+                return false;
+            }
+            self.charge_transfer_fee(paid, moves);
+            true
+        }
+
+        /// Moves every (recipient, token_id) entry from the caller, emitting the events in input order
+        fn batch_move(&mut self, transfers: Vec<(AccountId, u64)>) -> bool {
+            if transfers.len() as u64 > *self.max_batch_size {
+                return false;
+            }
+
+            // check every entry before moving anything
+            let caller = env.caller();
+            for (index, (to, token_id)) in transfers.iter().enumerate() {
+                if self.check_transfer(&caller, to, *token_id).is_err() {
+                    return false;
+                }
+                if transfers[..index].iter().any(|(_, other)| other == token_id) {
+                    return false;
+                }
+            }
+
+            // every move was checked above, so the tokens are reassigned without checking again
+            for (to, token_id) in transfers.iter() {
+                self.reassign_token(caller, *to, *token_id);
+                env.emit(EventTransfer { from: caller, to: *to, token_id: *token_id });
+            }
+            true
+        }
+
+        /// Whether an account may mint: the contract owner or an enabled minter
+        fn may_mint(&self, account: &AccountId) -> bool {
+            *account == *self.owner || self.minters.get(account).is_some() || self.mint_quotas.get(account).is_some()
+        }
+
+        /// Whether account may mint value tokens, accounts minting through a quota are limited by it
+        fn within_mint_quota(&self, account: &AccountId, value: u64) -> bool {
+            if *account == *self.owner || self.minters.get(account).is_some() {
+                return true;
+            }
+            value <= *self.mint_quotas.get(account).unwrap_or(&0)
+        }
+
+        /// Takes value minted tokens off the quota of account, if it mints through one
+        fn use_mint_quota(&mut self, account: AccountId, value: u64) {
+            if account == *self.owner || self.minters.get(&account).is_some() {
+                return;
+            }
+            let quota = *self.mint_quotas.get(&account).unwrap_or(&0);
+            if quota - value == 0 {
+                self.mint_quotas.remove(&account);
+            } else {
+                self.mint_quotas.insert(account, quota - value);
+            }
+        }
+
+        /// Transfers a token of the caller, the transfer fee has to be sent along with the call
+        fn paid_transfer(&mut self, to: AccountId, token_id: u64, tipped: bool) -> bool {
+            let paid = self.value_transferred();
+            if !self.covers_transfer_fee(paid, 1) {
+                self.credit(env.caller(), paid);
+                return self.operation_failed(OpCode::Transfer, token_id);
+            }
+
+            // carry out the actual transfer
+            if self.transfer_impl(env.caller(), to, token_id).is_ok() {
+                env.emit(EventTransfer { from: env.caller(), to: to, token_id: token_id });
+                if !tipped {
+                    self.charge_transfer_fee(paid, 1);
+                    return true;
+                }
+                // with a tip, anything paid beyond the fee goes to the creator
+                let fee = *self.transfer_fee;
+                self.collect_transfer_fee(fee);
+                let tip = paid - Balance::from(fee);
+                if tip > 0 {
+                    let recipient = *self.royalty_recipient;
+                    let creator = *self.creators.get(&token_id).unwrap_or(&recipient);
+                    self.credit(creator, tip);
+                    env.emit(EventTip { token_id: token_id, from: env.caller(), creator: creator, amount: tip });
+                }
+                return true;
+            }
+            // refund the fee of a failed transfer
+            self.credit(env.caller(), paid);
+            self.operation_failed(OpCode::Transfer, token_id)
+        }
+
+        /// Whether paid covers the transfer fee of moves transfers
+        fn covers_transfer_fee(&self, paid: Balance, moves: u64) -> bool {
+            (*self.transfer_fee).checked_mul(moves).map_or(false, |fee| paid >= Balance::from(fee))
+        }
+
+        /// Takes the transfer fee of moves transfers out of paid, refunding the rest to the caller
+        ///
+        /// paid must be checked with covers_transfer_fee first.
+        fn charge_transfer_fee(&mut self, paid: Balance, moves: u64) {
+            let fee = *self.transfer_fee * moves;
+            self.collect_transfer_fee(fee);
+            self.credit(env.caller(), paid - Balance::from(fee));
+        }
+
+        /// Credits a collected transfer fee to the fee recipient
+        fn collect_transfer_fee(&mut self, fee: u64) {
+            let recipient = *self.royalty_recipient;
+            self.credit(recipient, Balance::from(fee));
+            self.total_revenue += fee;
+        }
+
+        /// Stores the price a token was sold for and logs the sale
+        fn record_sale(&mut self, token_id: u64, from: AccountId, to: AccountId, price: u64) {
+            self.last_sale_price.insert(token_id, price);
+            env.emit(EventSale { token_id: token_id, from: from, to: to, price: price });
+        }
+
+        /// Index of the revealed range a token id falls in, if any
+        fn revealed_range_of(&self, token_id: u64) -> Option<u64> {
+            (0..*self.reveal_range_count).find(|index| {
+                let (first, last) = *self.reveal_ranges.get(index).unwrap();
+                first <= token_id && token_id <= last
+            })
+        }
+
+        /// Seed derived from the block number, the timestamp and the caller
+        ///
+        /// Only for weak randomness: block authors can predict and influence
+        /// it, so it must never decide anything of value.
+        fn weak_seed(&self) -> [u8; 32] {
+            let mut input = Vec::new();
+            input.extend_from_slice(&self.block_number().to_le_bytes());
+            input.extend_from_slice(&env.now().to_le_bytes());
+            input.extend_from_slice(&env.caller().encode());
+            keccak256(&input)
+        }
+
+        /// Credits the seller the price of a sold token minus its royalty, which goes to the royalty recipient
+        fn pay_out_sale(&mut self, token_id: u64, seller: AccountId, price: u64) {
+            let royalty_bps = *self.token_royalties.get(&token_id).unwrap_or(&0);
+            let royalty = (price as u128 * royalty_bps as u128 / 10000) as u64;
+            let recipient = *self.royalty_recipient;
+            self.credit(recipient, Balance::from(royalty));
+            self.total_revenue += royalty;
+            self.credit(seller, Balance::from(price - royalty));
+        }
+
+        /// Return a token followed by the tokens it is attached to, up to its root parent
+        fn ancestors(&self, token_id: u64) -> Vec<u64> {
+            let mut chain = Vec::new();
+            chain.push(token_id);
+            let mut current = token_id;
+            while let Some(parent_id) = self.parent_of.get(&current) {
+                current = *parent_id;
+                chain.push(current);
+            }
+            chain
+        }
+
+        /// Return the number of levels of tokens attached below a token
+        fn subtree_height(&self, token_id: u64) -> u64 {
+            let count = *self.child_count.get(&token_id).unwrap_or(&0);
+            let mut height = 0;
+            for index in 0..count {
+                let child_id = *self.children.get(&(token_id, index)).unwrap();
+                height = core::cmp::max(height, self.subtree_height(child_id) + 1);
+            }
+            height
+        }
+
+        /// Adds a token to the enumeration of tokens attached to a parent
+        fn add_child(&mut self, parent_id: u64, child_id: u64) {
+            let count = *self.child_count.get(&parent_id).unwrap_or(&0);
+            self.children.insert((parent_id, count), child_id);
+            self.children_index.insert(child_id, count);
+            self.child_count.insert(parent_id, count + 1);
+        }
+
+        /// Removes a token from the enumeration of tokens attached to a parent, moving the last one into its slot
+        fn remove_child(&mut self, parent_id: u64, child_id: u64) {
+            let index = self.children_index.remove(&child_id).unwrap();
+            let last_index = *self.child_count.get(&parent_id).unwrap() - 1;
+            if index != last_index {
+                let last_child = *self.children.get(&(parent_id, last_index)).unwrap();
+                self.children.insert((parent_id, index), last_child);
+                self.children_index.insert(last_child, index);
+            }
+            self.children.remove(&(parent_id, last_index));
+            // a parent without children has no count, so it can be burned again
+            if last_index > 0 {
+                self.child_count.insert(parent_id, last_index);
+            } else {
+                self.child_count.remove(&parent_id);
+            }
+        }
+
+        /// Return the outermost token a token is attached to, the token itself if unattached
+        fn root_token(&self, token_id: u64) -> u64 {
+            *self.ancestors(token_id).last().unwrap()
+        }
+
+        /// Metadata URI of a token, see token_uri
+        fn uri_of(&self, token_id: u64) -> Vec<u8> {
+            if self.token_data.get(&token_id).is_none() {
+                return Vec::new();
+            }
+            if let Some(uri) = self.token_uris.get(&token_id) {
+                return uri.clone();
+            }
+            let mut uri = match self.revealed_range_of(token_id) {
+                Some(index) => self.reveal_range_uris.get(&index).unwrap().clone(),
+                None => (*self.base_uri).clone(),
+            };
+            if uri.is_empty() {
+                return Vec::new();
+            }
+            let index = *self.reveal_index.get(&token_id).unwrap_or(&token_id);
+            append_decimal(&mut uri, index);
+            uri
+        }
+
+        /// Swaps token_a of the caller with token_b of counterparty, see swap
+        fn swap_impl(&mut self, token_a: u64, counterparty: AccountId, token_b: u64) -> bool {
+            let caller = env.caller();
+            if caller == counterparty {
+                return false;
+            }
+            if !self.is_token_owner(&caller, token_a) || !self.is_token_owner(&counterparty, token_b) {
+                return false;
+            }
+
+            let contract = env.account_id();
+            let approved = self.is_approved_spender(token_b, &caller)
+                || self.is_operator(&counterparty, &caller)
+                || self.is_approved_spender(token_b, &contract)
+                || self.is_operator(&counterparty, &contract);
+            if !approved {
+                return false;
+            }
+
+            // check both legs up front so the swap is all or nothing
+            if !self.transfer_allowed(&caller, token_a, &counterparty) || !self.transfer_allowed(&counterparty, token_b, &caller) {
+                return false;
+            }
+
+            self.reassign_token(caller, counterparty, token_a);
+            self.reassign_token(counterparty, caller, token_b);
+            env.emit(EventTransfer { from: caller, to: counterparty, token_id: token_a });
+            env.emit(EventTransfer { from: counterparty, to: caller, token_id: token_b });
+            true
+        }
+
+        /// Moves an escrowed token to its recipient, see claim
+        fn claim_impl(&mut self, token_id: u64, preimage: Vec<u8>) -> bool {
+            let hashlock = self.hashlocks.get(&token_id);
+            if let None = hashlock {
+                return false;
+            }
+            let (owner, to, hashlock, expires_at) = *hashlock.unwrap();
+            if env.now() >= expires_at || keccak256(&preimage) != hashlock {
+                return false;
+            }
+
+            self.hashlocks.remove(&token_id);
+            if !self.move_token(owner, to, token_id) {
+                self.hashlocks.insert(token_id, (owner, to, hashlock, expires_at));
+                return false;
+            }
+            env.emit(EventTransfer { from: owner, to: to, token_id: token_id });
+            true
+        }
+
+        /// Whether signature is a valid ed25519 signature of message by the voucher signer
+        fn signed_by_voucher_signer(&self, message: &[u8], signature: &[u8]) -> bool {
+            if *self.voucher_signer == [0x0; 32] {
+                return false;
+            }
+            let public_key = PublicKey::from_bytes(&*self.voucher_signer);
+            let signature = Signature::from_bytes(signature);
+            match (public_key, signature) {
+                (Ok(public_key), Ok(signature)) => public_key.verify::<Sha512>(message, &signature).is_ok(),
+                _ => false,
+            }
+        }
+
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    /// Layout of EventOperationFailed, for decoding emitted events
+    #[derive(Decode)]
+    struct OperationFailed {
+        caller: AccountId,
+        op_code: u8,
+        token_id: u64,
+    }
+
+    /// Deploys the test collection from alice with the default deploy-time settings
+    ///
+    /// Alice is [0x7; 32] rather than the environment's default caller, the
+    /// zero address, so that zero-address checks cannot pass by accident.
+    fn deploy_nftoken(init_value: u64, max_supply: u64) -> NFToken {
+        env::test::set_caller(AccountId::try_from([0x7; 32]).unwrap());
+        NFToken::deploy_mock(init_value, max_supply, b"NFToken".to_vec(), b"NFT".to_vec(), true, 1, 0, None)
+    }
+
+    /// Panics if the owner enumeration of any account disagrees with its balance
+    fn assert_owned_counts_match(nftoken: &NFToken, accounts: &[AccountId]) {
+        for account in accounts {
+            assert_eq!(nftoken.owned_count(*account), nftoken.balance_of(*account));
+        }
+    }
+
+    /// Panics if the live token and per-owner enumerations disagree with
+    /// each other or with ownership, or list a token twice
+    fn assert_enumeration_valid(nftoken: &NFToken) {
+        let tokens: Vec<u64> = (0..nftoken.total_supply())
+            .map(|index| nftoken.token_by_index(index))
+            .collect();
+        let mut live = tokens.clone();
+        live.sort();
+        live.dedup();
+        assert_eq!(live.len(), tokens.len());
+
+        let mut owned = Vec::new();
+        for (holder, balance) in nftoken.holders_snapshot(0, nftoken.holder_count()) {
+            assert_eq!(nftoken.owned_count(holder), balance);
+            for index in 0..balance {
+                let token_id = nftoken.token_of_owner_by_index(holder, index);
+                assert_eq!(nftoken.are_owned_by(holder, vec![token_id]), vec![true]);
+                owned.push(token_id);
+            }
+        }
+        owned.sort();
+        assert_eq!(owned, live);
+    }
+
+    #[test]
+    fn it_works() {
+
+        // deploying and miting initial tokens
+        let mut _nftoken = deploy_nftoken(100, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        let total_minted = _nftoken.total_minted();
+        assert_eq!(total_minted, 100);
+
+        // transferring token_id from alice to bob
+        _nftoken.transfer(bob, 1);
+
+        let alice_balance = _nftoken.balance_of(alice);
+        let mut bob_balance = _nftoken.balance_of(bob);
+
+        assert_eq!(alice_balance, 99);
+        assert_eq!(bob_balance, 1);
+
+        // approve charlie to send token_id 2 from alice's account
+        _nftoken.approval(charlie, 2, true);
+        assert_eq!(_nftoken.is_approved(2, charlie), true);
+
+        // overwrite charlie's approval with dave's approval
         _nftoken.approval(dave, 2, true);
         assert_eq!(_nftoken.is_approved(2, dave), true);
 
-        // remove dave from approvals
-        _nftoken.approval(dave, 2, false);
-        assert_eq!(_nftoken.is_approved(2, dave), false);
+        // remove dave from approvals
+        _nftoken.approval(dave, 2, false);
+        assert_eq!(_nftoken.is_approved(2, dave), false);
+
+        // transfer_from function: caller is token owner
+        _nftoken.approval(charlie, 3, true);
+        assert_eq!(_nftoken.is_approved(3, charlie), true);
+
+        _nftoken.transfer_from(bob, 3);
+        bob_balance = _nftoken.balance_of(bob);
+
+        assert_eq!(bob_balance, 2);
+    }
+
+    #[test]
+    fn terminate_is_owner_only() {
+        let mut _nftoken = deploy_nftoken(10, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        // bob is not the owner: the call is ignored and state stays intact
+        env::test::set_caller(bob);
+        _nftoken.terminate();
+
+        assert_eq!(_nftoken.total_minted(), 10);
+        assert_eq!(_nftoken.balance_of(alice), 10);
+    }
+
+    #[test]
+    fn transfer_updates_last_transfer_at() {
+        env::test::set_now(100);
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        // minting records the mint time
+        assert_eq!(_nftoken.last_transfer_at(1), 100);
+        assert_eq!(_nftoken.last_transfer_at(2), 100);
+        assert_eq!(_nftoken.last_transfer_at(3), 0);
+
+        env::test::set_now(250);
+        assert_eq!(_nftoken.transfer(bob, 1), true);
+
+        assert_eq!(_nftoken.last_transfer_at(1), 250);
+        assert_eq!(_nftoken.last_transfer_at(2), 100);
+    }
+
+    #[test]
+    fn balances_of_works() {
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        assert_eq!(_nftoken.mint(bob, 3), true);
+
+        let balances = _nftoken.balances_of(vec![alice, bob, charlie]);
+        assert_eq!(balances, vec![2, 3, 0]);
+    }
+
+    #[test]
+    fn approval_state_works() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let zero = AccountId::try_from([0x0; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        // approved: charlie may send token 2
+        _nftoken.approval(charlie, 2, true);
+        assert_eq!(_nftoken.approval_state(2), (charlie, false));
+
+        // unapproved
+        assert_eq!(_nftoken.approval_state(3), (zero, false));
+
+        // operator only: dave may send all of the owner's tokens
+        assert_eq!(_nftoken.set_approval_for_all(dave, true), true);
+        assert_eq!(_nftoken.is_approved_for_all(alice, dave), true);
+        assert_eq!(_nftoken.approval_state(3), (zero, true));
+    }
+
+    #[test]
+    fn batch_mint_rejects_overflowing_total() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        // the amounts alone sum past u64::MAX
+        let result = _nftoken.batch_mint(vec![bob, charlie], vec![u64::max_value(), 2]);
+        assert_eq!(result, false);
+
+        // nothing was minted
+        assert_eq!(_nftoken.total_minted(), 1);
+        assert_eq!(_nftoken.balance_of(bob), 0);
+        assert_eq!(_nftoken.balance_of(charlie), 0);
+
+        // a valid batch still goes through
+        assert_eq!(_nftoken.batch_mint(vec![bob, charlie], vec![2, 3]), true);
+        assert_eq!(_nftoken.total_minted(), 6);
+        assert_eq!(_nftoken.balances_of(vec![bob, charlie]), vec![2, 3]);
+    }
+
+    #[test]
+    fn unauthorized_mint_emits_failure_event() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        let events_before = env::test::emitted_events().count();
+
+        // bob is not the owner
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.mint(bob, 1), false);
+
+        let events = env::test::emitted_events().collect::<Vec<_>>();
+        assert_eq!(events.len(), events_before + 1);
+
+        let failed = OperationFailed::decode(&mut &events.last().unwrap()[1..]).unwrap();
+        assert_eq!((failed.caller, failed.op_code, failed.token_id), (bob, OpCode::Mint as u8, 0));
+    }
+
+    #[test]
+    fn failed_transfers_emit_failure_events() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        // both entrypoints log a validation failure the same way
+        _nftoken.pause(0, Vec::new());
+        assert_eq!(_nftoken.transfer(bob, 1), false);
+        let event = env::test::emitted_events().last().unwrap();
+        let failed = OperationFailed::decode(&mut &event[1..]).unwrap();
+        assert_eq!((failed.caller, failed.op_code, failed.token_id), (alice, OpCode::Transfer as u8, 1));
+
+        assert_eq!(_nftoken.transfer_from(bob, 1), false);
+        let event = env::test::emitted_events().last().unwrap();
+        let failed = OperationFailed::decode(&mut &event[1..]).unwrap();
+        assert_eq!((failed.caller, failed.op_code, failed.token_id), (alice, OpCode::TransferFrom as u8, 1));
+    }
+
+    #[test]
+    fn pause_expires_automatically() {
+        env::test::set_now(100);
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.pause(150, Vec::new()), true);
+        assert_eq!(_nftoken.is_paused(), true);
+        assert_eq!(_nftoken.transfer(bob, 1), false);
+
+        // advance past the expiry
+        env::test::set_now(200);
+        assert_eq!(_nftoken.is_paused(), false);
+        assert_eq!(_nftoken.transfer(bob, 1), true);
+        assert_eq!(_nftoken.balance_of(bob), 1);
+    }
+
+    #[test]
+    fn holders_snapshot_paginates() {
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        _nftoken.mint(bob, 3);
+        _nftoken.mint(charlie, 1);
+        assert_eq!(_nftoken.holder_count(), 3);
+
+        let mut snapshot = _nftoken.holders_snapshot(0, 2);
+        assert_eq!(snapshot.len(), 2);
+        snapshot.extend(_nftoken.holders_snapshot(2, 2));
+        assert_eq!(snapshot.len(), 3);
+
+        let total: u64 = snapshot.iter().map(|(_, balance)| balance).sum();
+        assert_eq!(total, _nftoken.total_minted());
+
+        // a holder whose balance drops to zero leaves the index
+        env::test::set_caller(charlie);
+        _nftoken.transfer(bob, 6);
+        assert_eq!(_nftoken.holder_count(), 2);
+    }
+
+    #[test]
+    fn transfer_requires_fee() {
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let treasury = AccountId::try_from([0x9; 32]).unwrap();
+
+        _nftoken.set_transfer_fee(10);
+        _nftoken.set_royalty_recipient(treasury);
+
+        // underpaying fails, ownership stays and the payment is refundable
+        env::test::set_value_transferred(9);
+        assert_eq!(_nftoken.transfer(bob, 1), false);
+        assert_eq!(_nftoken.balance_of(bob), 0);
+        assert_eq!(_nftoken.balance_of(alice), 2);
+        assert_eq!(_nftoken.payments_of(alice), 9);
+        assert_eq!(_nftoken.payments_of(treasury), 0);
+
+        // paying the exact fee succeeds and credits the treasury
+        env::test::set_value_transferred(10);
+        assert_eq!(_nftoken.transfer(bob, 1), true);
+        assert_eq!(_nftoken.balance_of(bob), 1);
+        assert_eq!(_nftoken.payments_of(treasury), 10);
+
+        // only the fee is taken from an overpayment, the rest is refundable
+        env::test::set_value_transferred(25);
+        assert_eq!(_nftoken.transfer(bob, 2), true);
+        assert_eq!(_nftoken.payments_of(treasury), 20);
+        assert_eq!(_nftoken.payments_of(alice), 9 + 15);
+    }
+
+    #[test]
+    fn allowlist_restricts_recipients() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        _nftoken.set_recipient_allowed(bob, true);
+        assert_eq!(_nftoken.is_recipient_allowed(bob), true);
+
+        // not enforced: anyone can receive
+        assert_eq!(_nftoken.transfer(charlie, 1), true);
+
+        // enforced: only allowlisted recipients can receive
+        _nftoken.set_enforce_allowlist(true);
+        assert_eq!(_nftoken.transfer(charlie, 2), false);
+        assert_eq!(_nftoken.transfer(bob, 2), true);
+
+        assert_eq!(_nftoken.balance_of(bob), 1);
+        assert_eq!(_nftoken.balance_of(charlie), 1);
+    }
+
+    #[test]
+    fn is_approved_for_all_batch_works() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        _nftoken.set_approval_for_all(bob, true);
+        _nftoken.set_approval_for_all(dave, true);
+
+        let approved = _nftoken.is_approved_for_all_batch(alice, vec![bob, charlie, dave]);
+        assert_eq!(approved, vec![true, false, true]);
+    }
+
+    #[test]
+    fn mint_with_external_id_works() {
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let external_ref = [0x7; 32];
+
+        assert_eq!(_nftoken.id_of_ref(external_ref), None);
+        let token_id = _nftoken.mint_with_external_id(bob, external_ref);
+        assert_eq!(token_id, Some(3));
+        assert_eq!(_nftoken.balance_of(bob), 1);
+
+        // both directions of the mapping are stored
+        assert_eq!(_nftoken.id_of_ref(external_ref), Some(3));
+        assert_eq!(_nftoken.ref_of_id(3), external_ref);
+
+        // a reference can only be used once
+        assert_eq!(_nftoken.mint_with_external_id(bob, external_ref), None);
+        assert_eq!(_nftoken.total_minted(), 3);
+    }
+
+    #[test]
+    fn external_ids_can_map_to_token_zero() {
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+        let mut _nftoken = NFToken::deploy_mock(0, 0, b"NFToken".to_vec(), b"NFT".to_vec(), true, 0, 0, None);
+        let external_ref = [0x7; 32];
+
+        assert_eq!(_nftoken.mint_with_external_id(bob, external_ref), Some(0));
+        assert_eq!(_nftoken.id_of_ref(external_ref), Some(0));
+        assert_eq!(_nftoken.id_of_ref([0x8; 32]), None);
+    }
+
+    #[test]
+    fn auto_operator_is_approved_on_mint() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let zero = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let market = AccountId::try_from([0x8; 32]).unwrap();
+
+        _nftoken.set_auto_operator(market);
+        _nftoken.mint(bob, 1);
+        assert_eq!(_nftoken.is_approved_for_all(bob, market), true);
+
+        // the zero address disables it
+        _nftoken.set_auto_operator(zero);
+        _nftoken.mint(charlie, 1);
+        assert_eq!(_nftoken.is_approved_for_all(charlie, market), false);
+    }
+
+    #[test]
+    fn transfer_from_scenarios() {
+        let mut _nftoken = deploy_nftoken(4, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        // owner moves its own token
+        assert_eq!(_nftoken.transfer_from(bob, 1), true);
+        assert_eq!(_nftoken.owner_of(1), bob);
+
+        // approved spender moves the token from its owner
+        _nftoken.approval(charlie, 2, true);
+        _nftoken.set_approval_for_all(dave, true);
+        env::test::set_caller(charlie);
+        assert_eq!(_nftoken.transfer_from(charlie, 2), true);
+        assert_eq!(_nftoken.owner_of(2), charlie);
+
+        // unauthorized callers and unminted tokens fail
+        assert_eq!(_nftoken.transfer_from(charlie, 3), false);
+        assert_eq!(_nftoken.transfer_from(charlie, 99), false);
+        assert_eq!(_nftoken.owner_of(3), alice);
+
+        // operator moves any token of the owner
+        env::test::set_caller(dave);
+        assert_eq!(_nftoken.transfer_from(dave, 3), true);
+        assert_eq!(_nftoken.owner_of(3), dave);
+
+        assert_eq!(_nftoken.balances_of(vec![alice, bob, charlie, dave]), vec![1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn approvals_expire() {
+        env::test::set_now(100);
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        _nftoken.approve_until(charlie, 1, 200);
+        _nftoken.approve_until(charlie, 2, 200);
+
+        // future expiry: the approval is valid
+        assert_eq!(_nftoken.is_approved(1, charlie), true);
+        env::test::set_caller(charlie);
+        assert_eq!(_nftoken.transfer_from(charlie, 1), true);
+
+        // past the expiry: the approval is treated as absent
+        env::test::set_now(200);
+        assert_eq!(_nftoken.is_approved(2, charlie), false);
+        assert_eq!(_nftoken.transfer_from(charlie, 2), false);
+        assert_eq!(_nftoken.balance_of(charlie), 1);
+    }
+
+    #[test]
+    fn deploy_emits_event() {
+        #[derive(Decode)]
+        struct Deployed {
+            owner: AccountId,
+            name: Vec<u8>,
+            symbol: Vec<u8>,
+            init_value: u64,
+            max_supply: u64,
+        }
+
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        env::test::set_caller(alice);
+        let _nftoken = NFToken::deploy_mock(5, 10, b"Kitties".to_vec(), b"KIT".to_vec(), true, 1, 0, None);
+        assert_eq!(_nftoken.name(), b"Kitties".to_vec());
+        assert_eq!(_nftoken.symbol(), b"KIT".to_vec());
+
+        // the deploy event is the last one emitted, after the variant index byte
+        let event = env::test::emitted_events().last().unwrap();
+        let deployed = Deployed::decode(&mut &event[1..]).unwrap();
+        assert_eq!(deployed.owner, alice);
+        assert_eq!(deployed.name, b"Kitties".to_vec());
+        assert_eq!(deployed.symbol, b"KIT".to_vec());
+        assert_eq!(deployed.init_value, 5);
+        assert_eq!(deployed.max_supply, 10);
+    }
+
+    #[test]
+    fn token_uri_with_content_hash() {
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let uri = b"ipfs://token/1".to_vec();
+        let content_hash = [0x5; 32];
+
+        assert_eq!(_nftoken.set_token_uri_with_hash(1, uri.clone(), content_hash), true);
+        assert_eq!(_nftoken.token_uri(1), uri);
+        assert_eq!(_nftoken.content_hash_of(1), content_hash);
+
+        // unminted tokens have no metadata
+        assert_eq!(_nftoken.set_token_uri_with_hash(3, uri.clone(), content_hash), false);
+        assert_eq!(_nftoken.content_hash_of(3), [0x0; 32]);
+
+        // overwriting the URI resets the hash, one at a time or in a batch
+        assert_eq!(_nftoken.set_token_uri(1, b"ipfs://token/1b".to_vec()), true);
+        assert_eq!(_nftoken.content_hash_of(1), [0x0; 32]);
+        assert_eq!(_nftoken.set_token_uri_with_hash(2, uri.clone(), content_hash), true);
+        assert_eq!(_nftoken.set_token_uris(vec![2], vec![b"ipfs://token/2b".to_vec()]), true);
+        assert_eq!(_nftoken.content_hash_of(2), [0x0; 32]);
+    }
+
+    #[test]
+    fn burned_tokens_lose_their_metadata() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+
+        _nftoken.set_token_uri_with_hash(1, b"ipfs://token/1".to_vec(), [0x5; 32]);
+        _nftoken.set_attribute(1, b"color".to_vec(), b"red".to_vec());
+        assert_eq!(_nftoken.burn(1), true);
+
+        assert_eq!(_nftoken.token_uri(1), Vec::<u8>::new());
+        assert_eq!(_nftoken.content_hash_of(1), [0x0; 32]);
+        assert_eq!(_nftoken.attributes_of(1), Vec::new());
+    }
+
+    #[test]
+    fn swap_exchanges_tokens() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.mint(bob, 1);
+        _nftoken.approval(bob, 1, true);
+
+        // without bob's approval the swap fails
+        assert_eq!(_nftoken.swap(1, bob, 2), false);
+
+        // bob approves alice for token 2
+        env::test::set_caller(bob);
+        _nftoken.approval(alice, 2, true);
+
+        env::test::set_caller(alice);
+        assert_eq!(_nftoken.swap(1, bob, 2), true);
+        assert_eq!(_nftoken.owner_of(1), bob);
+        assert_eq!(_nftoken.owner_of(2), alice);
+
+        // both approvals were cleared
+        assert_eq!(_nftoken.is_approved(1, bob), false);
+        assert_eq!(_nftoken.is_approved(2, alice), false);
+    }
+
+    #[test]
+    fn is_sold_out_works() {
+        let mut _nftoken = deploy_nftoken(0, 3);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.mint(bob, 2);
+        assert_eq!(_nftoken.is_sold_out(), false);
+
+        _nftoken.mint(bob, 1);
+        assert_eq!(_nftoken.is_sold_out(), true);
+        assert_eq!(_nftoken.mint(bob, 1), false);
+    }
+
+    #[test]
+    fn mint_range_works() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.mint_range(alice, 100, 105), true);
+        assert_eq!(_nftoken.balance_of(alice), 6);
+        assert_eq!(_nftoken.total_minted(), 6);
+        assert_eq!(_nftoken.owner_of(100), alice);
+        assert_eq!(_nftoken.owner_of(105), alice);
+
+        // 103 is already taken: nothing in the range is minted
+        assert_eq!(_nftoken.mint_range(bob, 103, 110), false);
+        assert_eq!(_nftoken.balance_of(bob), 0);
+        assert_eq!(_nftoken.owner_of(110), zero_account());
+        assert_eq!(_nftoken.total_minted(), 6);
+
+        // a burned id cannot be minted again
+        assert_eq!(_nftoken.burn(105), true);
+        assert_eq!(_nftoken.mint_range(bob, 105, 106), false);
+        assert_eq!(_nftoken.owner_of(106), zero_account());
+    }
+
+    #[test]
+    fn sequential_mints_skip_burned_range_ids() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+
+        // 3 is minted ahead of the sequence and burned before the sequence gets there
+        assert_eq!(_nftoken.mint_range(alice, 3, 3), true);
+        assert_eq!(_nftoken.burn(3), true);
+        assert_eq!(_nftoken.mint(alice, 3), true);
+
+        assert_eq!(_nftoken.owner_of(1), alice);
+        assert_eq!(_nftoken.owner_of(2), alice);
+        assert_eq!(_nftoken.owner_of(3), zero_account());
+        assert_eq!(_nftoken.owner_of(4), alice);
+        assert_eq!(_nftoken.token_flags(3), (false, false, true));
+    }
+
+    #[test]
+    fn mint_range_respects_minters_and_quotas() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.mint_range(bob, 100, 101), false);
+
+        env::test::set_caller(AccountId::try_from([0x7; 32]).unwrap());
+        _nftoken.delegate_mint(charlie, 2);
+        env::test::set_caller(charlie);
+        assert_eq!(_nftoken.mint_range(charlie, 100, 102), false);
+        assert_eq!(_nftoken.mint_range(charlie, 100, 101), true);
+        assert_eq!(_nftoken.balance_of(charlie), 2);
+        assert_eq!(_nftoken.mint_range(charlie, 102, 102), false);
+    }
+
+    #[test]
+    fn pause_reason_is_cleared_on_unpause() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+
+        _nftoken.pause(0, b"migrating metadata".to_vec());
+        assert_eq!(_nftoken.pause_reason(), b"migrating metadata".to_vec());
+
+        _nftoken.unpause();
+        assert_eq!(_nftoken.is_paused(), false);
+        assert_eq!(_nftoken.pause_reason(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn sale_phases_gate_mints() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.set_mint_allowlisted(bob, true);
+
+        // closed: nothing works
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.allowlist_mint(1), false);
+        assert_eq!(_nftoken.public_mint(1), false);
+
+        // allowlist phase
+        env::test::set_caller(alice);
+        _nftoken.set_sale_phase(1);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.allowlist_mint(1), true);
+        assert_eq!(_nftoken.public_mint(1), false);
+
+        // public phase
+        env::test::set_caller(alice);
+        _nftoken.set_sale_phase(2);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.allowlist_mint(1), false);
+        assert_eq!(_nftoken.public_mint(1), true);
+
+        assert_eq!(_nftoken.balance_of(bob), 2);
+    }
+
+    #[test]
+    fn locked_token_cannot_be_moved_by_owner() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let market = AccountId::try_from([0x8; 32]).unwrap();
+
+        _nftoken.approval(market, 1, true);
+
+        env::test::set_caller(market);
+        assert_eq!(_nftoken.lock_for_sale(1), true);
+        assert_eq!(_nftoken.locked_by(1), market);
+
+        // the owner cannot unlock or move the token
+        env::test::set_caller(alice);
+        assert_eq!(_nftoken.unlock(1), false);
+        assert_eq!(_nftoken.transfer(bob, 1), false);
+
+        env::test::set_caller(market);
+        assert_eq!(_nftoken.unlock(1), true);
+
+        env::test::set_caller(alice);
+        assert_eq!(_nftoken.transfer(bob, 1), true);
+        assert_eq!(_nftoken.owner_of(1), bob);
+    }
+
+    #[test]
+    fn per_token_mint_events_can_be_disabled() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        // one EventMint plus a Transfer per token
+        let events_before = env::test::emitted_events().count();
+        _nftoken.batch_mint(vec![bob], vec![5]);
+        assert_eq!(env::test::emitted_events().count() - events_before, 6);
+
+        // only the aggregate EventMint
+        _nftoken.set_emit_per_token_events(false);
+        let events_before = env::test::emitted_events().count();
+        _nftoken.batch_mint(vec![bob], vec![5]);
+        assert_eq!(env::test::emitted_events().count() - events_before, 1);
+    }
+
+    #[test]
+    fn contract_account_is_stable() {
+        let _nftoken = deploy_nftoken(0, 0);
+        let zero = AccountId::try_from([0x0; 32]).unwrap();
+
+        let account = _nftoken.contract_account();
+        assert_ne!(account, zero);
+        assert_eq!(_nftoken.contract_account(), account);
+    }
+
+    #[test]
+    fn staked_token_cannot_be_transferred() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.stake(1), true);
+        assert_eq!(_nftoken.staked_at(1), 1000);
+        assert_eq!(_nftoken.transfer(bob, 1), false);
+
+        // only the staker can unstake
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.unstake(1), 0);
+        assert_eq!(_nftoken.staked_at(1), 1000);
+
+        env::test::set_caller(AccountId::try_from([0x7; 32]).unwrap());
+        env::test::set_now(1600);
+        assert_eq!(_nftoken.unstake(1), 600);
+        assert_eq!(_nftoken.unstake(1), 0);
+        assert_eq!(_nftoken.staked_at(1), 0);
+        assert_eq!(_nftoken.transfer(bob, 1), true);
+    }
+
+    #[test]
+    fn tokens_held_in_place_cannot_be_staked_or_shared() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let contract = _nftoken.contract_account();
+        let shares = vec![(bob, 5000), (charlie, 5000)];
+
+        // under auction
+        _nftoken.approval(contract, 1, true);
+        assert_eq!(_nftoken.create_auction(1, 100, 2000), true);
+        assert_eq!(_nftoken.stake(1), false);
+        assert_eq!(_nftoken.set_shares(1, shares.clone()), false);
+
+        // escrowed behind a hashlock
+        assert_eq!(_nftoken.transfer_with_hashlock(bob, 2, keccak256(b"secret"), 2000), true);
+        assert_eq!(_nftoken.stake(2), false);
+        assert_eq!(_nftoken.set_shares(2, shares.clone()), false);
+
+        // frozen
+        _nftoken.freeze(3);
+        assert_eq!(_nftoken.stake(3), false);
+        assert_eq!(_nftoken.set_shares(3, shares.clone()), false);
+        _nftoken.unfreeze(3);
+        assert_eq!(_nftoken.stake(3), true);
+        assert_eq!(_nftoken.staked_at(3), 1000);
+    }
+
+    #[test]
+    fn transfer_errors_are_distinct() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.mint(bob, 1);
+
+        // never minted
+        assert_eq!(_nftoken.can_transfer(alice, bob, 99), Err(Error::TokenNotFound));
+        // owned by someone else
+        assert_eq!(_nftoken.can_transfer(alice, bob, 2), Err(Error::NotOwner));
+        assert_eq!(_nftoken.can_transfer(alice, bob, 1), Ok(()));
+
+        assert_eq!(_nftoken.transfer(bob, 99), false);
+        assert_eq!(_nftoken.transfer(bob, 2), false);
+    }
+
+    #[test]
+    fn minted_to_ignores_burns_and_transfers() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.mint(alice, 3);
+        assert_eq!(_nftoken.burn(1), true);
+        _nftoken.transfer(bob, 2);
+
+        assert_eq!(_nftoken.minted_to(alice), 3);
+        assert_eq!(_nftoken.minted_to(bob), 0);
+        assert_eq!(_nftoken.balance_of(alice), 1);
+        assert_eq!(_nftoken.total_supply(), 2);
+        assert_eq!(_nftoken.total_minted(), 3);
+    }
+
+    #[test]
+    fn supply_stats_are_consistent() {
+        let mut _nftoken = deploy_nftoken(4, 10);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.mint(bob, 2);
+        _nftoken.burn(1);
+        _nftoken.burn(2);
+
+        let (total_minted, total_supply, max_supply, remaining) = _nftoken.supply_stats();
+        assert_eq!(total_minted, 6);
+        assert_eq!(total_supply, 4);
+        assert_eq!(max_supply, 10);
+        assert_eq!(remaining, 4);
+        assert_eq!(total_minted + remaining, max_supply);
+    }
+
+    #[test]
+    fn distribute_moves_all_tokens() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        assert_eq!(_nftoken.distribute(vec![(bob, 1), (charlie, 2)]), true);
+        assert_eq!(_nftoken.owner_of(1), bob);
+        assert_eq!(_nftoken.owner_of(2), charlie);
+        assert_eq!(_nftoken.balance_of(alice), 1);
+
+        // token 1 is no longer alice's: nothing moves
+        assert_eq!(_nftoken.distribute(vec![(bob, 3), (charlie, 1)]), false);
+        assert_eq!(_nftoken.owner_of(3), alice);
+
+        // over the batch size
+        _nftoken.set_max_batch_size(1);
+        assert_eq!(_nftoken.distribute(vec![(bob, 3), (charlie, 3)]), false);
+    }
+
+    #[test]
+    fn batch_transfers_require_fee_per_token() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let treasury = AccountId::try_from([0x9; 32]).unwrap();
+
+        _nftoken.set_transfer_fee(10);
+        _nftoken.set_royalty_recipient(treasury);
+
+        // without payment nothing moves
+        assert_eq!(_nftoken.distribute(vec![(bob, 1), (charlie, 2)]), false);
+        assert_eq!(_nftoken.owner_of(1), alice);
+        assert_eq!(_nftoken.owner_of(2), alice);
+
+        // the fee of a single transfer does not cover two, the payment is refundable
+        env::test::set_value_transferred(10);
+        assert_eq!(_nftoken.distribute(vec![(bob, 1), (charlie, 2)]), false);
+        assert_eq!(_nftoken.balance_of(alice), 3);
+        assert_eq!(_nftoken.payments_of(alice), 10);
+        assert_eq!(_nftoken.payments_of(treasury), 0);
+
+        // the excess over the fees is refundable
+        env::test::set_value_transferred(25);
+        assert_eq!(_nftoken.distribute(vec![(bob, 1), (charlie, 2)]), true);
+        assert_eq!(_nftoken.owner_of(1), bob);
+        assert_eq!(_nftoken.owner_of(2), charlie);
+        assert_eq!(_nftoken.payments_of(treasury), 20);
+        assert_eq!(_nftoken.payments_of(alice), 10 + 5);
+
+        env::test::set_value_transferred(0);
+        assert_eq!(_nftoken.batch_transfer(bob, vec![3]), false);
+        assert_eq!(_nftoken.owner_of(3), alice);
+    }
+
+    #[test]
+    fn receiver_optin_restricts_transfers() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(bob);
+        _nftoken.set_can_receive(true);
+        assert_eq!(_nftoken.can_receive(bob), true);
+
+        env::test::set_caller(alice);
+        // not enforced: anyone can receive
+        assert_eq!(_nftoken.transfer(charlie, 1), true);
+
+        // enforced: only opted-in receivers
+        _nftoken.set_enforce_receiver_optin(true);
+        assert_eq!(_nftoken.transfer(charlie, 2), false);
+        assert_eq!(_nftoken.transfer(bob, 2), true);
+    }
+
+    #[test]
+    fn total_active_approvals_is_maintained() {
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        _nftoken.approval(charlie, 1, true);
+        _nftoken.approval(charlie, 2, true);
+        // overwriting an approval does not count twice
+        _nftoken.approval(bob, 2, true);
+        assert_eq!(_nftoken.total_active_approvals(), 2);
+
+        // the transfer clears the approval of token 1
+        _nftoken.transfer(bob, 1);
+        assert_eq!(_nftoken.total_active_approvals(), 1);
+    }
+
+    #[test]
+    fn zero_account_helpers() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(is_zero(&zero_account()), true);
+        assert_eq!(is_zero(&bob), false);
+    }
+
+    #[test]
+    fn list_and_buy() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let treasury = AccountId::try_from([0x9; 32]).unwrap();
+        let contract = _nftoken.contract_account();
+
+        _nftoken.set_royalty_recipient(treasury);
+        _nftoken.set_royalty(500);
+        _nftoken.mint(alice, 2);
+
+        // listing requires the contract to be approved
+        assert_eq!(_nftoken.list(1, 1000), false);
+        _nftoken.set_approval_for_all(contract, true);
+        assert_eq!(_nftoken.list(1, 1000), true);
+        assert_eq!(_nftoken.list(2, 1000), true);
+
+        // insufficient payment fails and is refundable
+        env::test::set_caller(bob);
+        env::test::set_value_transferred(999);
+        assert_eq!(_nftoken.buy(1), false);
+        assert_eq!(_nftoken.owner_of(1), alice);
+        assert_eq!(_nftoken.payments_of(bob), 999);
+
+        // correct payment moves the token and splits the funds
+        env::test::set_value_transferred(1000);
+        assert_eq!(_nftoken.buy(1), true);
+        assert_eq!(_nftoken.owner_of(1), bob);
+        assert_eq!(_nftoken.payments_of(alice), 950);
+        assert_eq!(_nftoken.payments_of(treasury), 50);
+
+        // a delisted token cannot be bought
+        env::test::set_caller(alice);
+        assert_eq!(_nftoken.delist(2), true);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.buy(2), false);
+        assert_eq!(_nftoken.owner_of(2), alice);
+    }
+
+    #[test]
+    fn revoking_the_contract_approval_stops_a_listing() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let contract = _nftoken.contract_account();
+
+        _nftoken.set_approval_for_all(contract, true);
+        assert_eq!(_nftoken.list(1, 1000), true);
+        _nftoken.set_approval_for_all(contract, false);
+        assert_eq!(_nftoken.listing_of(1), (zero_account(), 0));
+
+        // the buyer's payment is refundable and the token stays
+        env::test::set_caller(bob);
+        env::test::set_value_transferred(1000);
+        assert_eq!(_nftoken.buy(1), false);
+        assert_eq!(_nftoken.owner_of(1), alice);
+        assert_eq!(_nftoken.payments_of(bob), 1000);
+        assert_eq!(_nftoken.payments_of(alice), 0);
+    }
+
+    #[test]
+    fn listing_lifecycle_emits_events() {
+        #[derive(Decode)]
+        struct Listed {
+            token_id: u64,
+            seller: AccountId,
+            price: u64,
+        }
+        #[derive(Decode)]
+        struct Delisted {
+            token_id: u64,
+        }
+        #[derive(Decode)]
+        struct Sold {
+            token_id: u64,
+            seller: AccountId,
+            buyer: AccountId,
+            price: u64,
+        }
+
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let contract = _nftoken.contract_account();
+        _nftoken.set_approval_for_all(contract, true);
+
+        // events are decoded after their variant index byte
+        _nftoken.list(1, 700);
+        let event = env::test::emitted_events().last().unwrap();
+        let listed = Listed::decode(&mut &event[1..]).unwrap();
+        assert_eq!((listed.token_id, listed.seller, listed.price), (1, alice, 700));
+
+        _nftoken.list(2, 300);
+        _nftoken.delist(2);
+        let event = env::test::emitted_events().last().unwrap();
+        assert_eq!(Delisted::decode(&mut &event[1..]).unwrap().token_id, 2);
+
+        env::test::set_caller(bob);
+        env::test::set_value_transferred(700);
+        _nftoken.buy(1);
+        let event = env::test::emitted_events().last().unwrap();
+        let sold = Sold::decode(&mut &event[1..]).unwrap();
+        assert_eq!((sold.token_id, sold.seller, sold.buyer, sold.price), (1, alice, bob, 700));
+    }
+
+    #[test]
+    fn listing_of_works() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let contract = _nftoken.contract_account();
+        _nftoken.set_approval_for_all(contract, true);
+
+        _nftoken.list(1, 500);
+        assert_eq!(_nftoken.listing_of(1), (alice, 500));
+
+        _nftoken.delist(1);
+        assert_eq!(_nftoken.listing_of(1), (zero_account(), 0));
+    }
+
+    #[test]
+    fn uri_length_is_bounded() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        assert_eq!(_nftoken.max_uri_len(), 256);
+
+        // exactly at the limit
+        assert_eq!(_nftoken.set_token_uri(1, vec![b'a'; 256]), true);
+        assert_eq!(_nftoken.set_base_uri(vec![b'a'; 256]), true);
+
+        // one over the limit
+        assert_eq!(_nftoken.set_token_uri(1, vec![b'b'; 257]), false);
+        assert_eq!(_nftoken.set_base_uri(vec![b'b'; 257]), false);
+        assert_eq!(_nftoken.token_uri(1), vec![b'a'; 256]);
+    }
+
+    #[test]
+    fn token_uri_falls_back_to_base_uri() {
+        let mut _nftoken = deploy_nftoken(12, 0);
+
+        _nftoken.set_base_uri(b"ipfs://base/".to_vec());
+        _nftoken.set_token_uri(1, b"ipfs://one".to_vec());
+
+        assert_eq!(_nftoken.token_uri(1), b"ipfs://one".to_vec());
+        assert_eq!(_nftoken.token_uri(12), b"ipfs://base/12".to_vec());
+        assert_eq!(_nftoken.token_uri(13), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn set_approval_for_all_batch_works() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        assert_eq!(_nftoken.set_approval_for_all_batch(vec![bob, charlie, dave], true), true);
+        assert_eq!(_nftoken.is_approved_for_all_batch(alice, vec![bob, charlie, dave]), vec![true, true, true]);
+
+        assert_eq!(_nftoken.set_approval_for_all_batch(vec![bob, dave], false), true);
+        assert_eq!(_nftoken.is_approved_for_all_batch(alice, vec![bob, charlie, dave]), vec![false, true, false]);
+    }
+
+    #[test]
+    fn tokens_approved_to_works() {
+        let mut _nftoken = deploy_nftoken(4, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        _nftoken.approval(charlie, 1, true);
+        _nftoken.approval(bob, 2, true);
+        _nftoken.approval(charlie, 3, true);
+
+        let mut tokens = _nftoken.tokens_approved_to(charlie);
+        tokens.sort();
+        assert_eq!(tokens, vec![1, 3]);
+    }
+
+    #[test]
+    fn deploy_logs_genesis_mint() {
+        #[derive(Decode)]
+        struct Mint {
+            owner: AccountId,
+            value: u64,
+            first_id: u64,
+            last_id: u64,
+        }
+
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let _nftoken = deploy_nftoken(5, 0);
+
+        // five Transfers from the zero address, EventMint, then EventDeployed
+        let events = env::test::emitted_events().collect::<Vec<_>>();
+        assert_eq!(events.len(), 7);
+
+        let mint = Mint::decode(&mut &events[5][1..]).unwrap();
+        assert_eq!(mint.owner, alice);
+        assert_eq!(mint.value, 5);
+        assert_eq!((mint.first_id, mint.last_id), (1, 5));
+    }
+
+    #[test]
+    fn expired_listings_cannot_be_bought() {
+        env::test::set_now(100);
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let contract = _nftoken.contract_account();
+        _nftoken.set_approval_for_all(contract, true);
+
+        assert_eq!(_nftoken.list_until(1, 500, 200), true);
+
+        // not expired yet: it cannot be cleared
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.clear_expired_listing(1), false);
+
+        // expired: buying fails and anyone can clear it
+        env::test::set_now(200);
+        env::test::set_value_transferred(500);
+        assert_eq!(_nftoken.buy(1), false);
+        assert_eq!(_nftoken.owner_of(1), alice);
+        assert_eq!(_nftoken.clear_expired_listing(1), true);
+        assert_eq!(_nftoken.listing_of(1), (zero_account(), 0));
+    }
+
+    #[test]
+    fn sale_config_works() {
+        let mut _nftoken = deploy_nftoken(0, 100);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.set_mint_price(25);
+        _nftoken.set_max_per_address(2);
+        _nftoken.set_sale_phase(2);
+        assert_eq!(_nftoken.sale_config(), (25, 2, 100, 2));
+
+        env::test::set_caller(bob);
+        env::test::set_value_transferred(50);
+        assert_eq!(_nftoken.public_mint(2), true);
+        // over the per-address limit
+        assert_eq!(_nftoken.public_mint(1), false);
+        assert_eq!(_nftoken.balance_of(bob), 2);
+    }
+
+    #[test]
+    fn reveal_shuffle_is_a_bijection() {
+        let mut _nftoken = deploy_nftoken(10, 0);
+        assert_eq!(_nftoken.reveal_index_of(1), 0);
+
+        assert_eq!(_nftoken.reveal_shuffle([0x42; 32]), true);
+
+        let mut indices: Vec<u64> = (1..=10).map(|token_id| _nftoken.reveal_index_of(token_id)).collect();
+        indices.sort();
+        assert_eq!(indices, (1..=10).collect::<Vec<u64>>());
+
+        // only once
+        assert_eq!(_nftoken.reveal_shuffle([0x43; 32]), false);
+    }
+
+    #[test]
+    fn are_owned_by_works() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.mint(alice, 2);
+        _nftoken.mint(bob, 1);
+
+        assert_eq!(_nftoken.are_owned_by(alice, vec![1, 2, 3]), vec![true, true, false]);
+    }
+
+    #[test]
+    fn transfer_to_self_is_rejected() {
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+
+        assert_eq!(_nftoken.can_transfer(alice, alice, 1), Err(Error::SelfTransfer));
+        assert_eq!(_nftoken.transfer(alice, 1), false);
+        assert_eq!(_nftoken.transfer_from(alice, 1), false);
+
+        assert_eq!(_nftoken.balance_of(alice), 2);
+        assert_eq!(_nftoken.owner_of(1), alice);
+    }
+
+    #[test]
+    fn holds_any_works() {
+        let _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.holds_any(alice), true);
+        assert_eq!(_nftoken.holds_any(bob), false);
+    }
+
+    #[test]
+    fn withdraw_splits_proceeds_among_payees() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        // shares must sum to 10000
+        assert_eq!(_nftoken.set_payees(vec![(bob, 7000), (charlie, 2000)]), false);
+        assert_eq!(_nftoken.set_payees(vec![(bob, 7000), (charlie, 3000)]), true);
+        assert_eq!(_nftoken.payees(), vec![(bob, 7000), (charlie, 3000)]);
+
+        _nftoken.set_mint_price(100);
+        _nftoken.set_sale_phase(2);
+        env::test::set_caller(dave);
+        env::test::set_value_transferred(1000);
+        assert_eq!(_nftoken.public_mint(10), true);
+
+        // only the contract owner splits the proceeds
+        assert_eq!(_nftoken.withdraw(), false);
+        env::test::set_caller(AccountId::try_from([0x7; 32]).unwrap());
+        assert_eq!(_nftoken.withdraw(), true);
+        assert_eq!(_nftoken.payments_of(bob), 700);
+        assert_eq!(_nftoken.payments_of(charlie), 300);
+        // nothing left to split
+        assert_eq!(_nftoken.withdraw(), false);
+    }
+
+    #[test]
+    fn metadata_update_events_work() {
+        #[derive(Decode)]
+        struct MetadataUpdate {
+            token_id: u64,
+        }
+        #[derive(Decode)]
+        struct BatchMetadataUpdate {
+            from_token_id: u64,
+            to_token_id: u64,
+        }
+
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.emit_metadata_update(2), true);
+        let event = env::test::emitted_events().last().unwrap();
+        let update = MetadataUpdate::decode(&mut &event[1..]).unwrap();
+        assert_eq!(update.token_id, 2);
+
+        assert_eq!(_nftoken.emit_metadata_update_range(1, 3), true);
+        let event = env::test::emitted_events().last().unwrap();
+        let update = BatchMetadataUpdate::decode(&mut &event[1..]).unwrap();
+        assert_eq!((update.from_token_id, update.to_token_id), (1, 3));
+
+        assert_eq!(_nftoken.emit_metadata_update(4), false);
+        assert_eq!(_nftoken.emit_metadata_update_range(3, 1), false);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.emit_metadata_update(1), false);
+        assert_eq!(_nftoken.emit_metadata_update_range(1, 3), false);
+    }
+
+    #[test]
+    fn packed_token_data_keeps_transfer_semantics() {
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        assert_eq!(_nftoken.approval(bob, 1, true), true);
+        assert_eq!(_nftoken.approval(bob, 2, true), true);
+        assert_eq!(_nftoken.total_active_approvals(), 2);
+
+        // the approved spender moves the token and its approval is dropped
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.transfer_from(charlie, 1), true);
+        assert_eq!(_nftoken.owner_of(1), charlie);
+        assert_eq!(_nftoken.is_approved(1, bob), false);
+        assert_eq!(_nftoken.total_active_approvals(), 1);
+        assert_eq!(_nftoken.transfer_from(alice, 1), false);
+
+        // the other token keeps its owner and approval
+        assert_eq!(_nftoken.owner_of(2), alice);
+        assert_eq!(_nftoken.is_approved(2, bob), true);
+
+        // the new owner can approve, and disapproving clears the entry
+        env::test::set_caller(charlie);
+        assert_eq!(_nftoken.approval(alice, 1, true), true);
+        assert_eq!(_nftoken.approval(alice, 1, false), true);
+        assert_eq!(_nftoken.is_approved(1, alice), false);
+        assert_eq!(_nftoken.owner_of(1), charlie);
+        assert_eq!(_nftoken.total_active_approvals(), 1);
+    }
+
+    #[test]
+    fn revoke_all_my_approvals_works() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        _nftoken.approval(bob, 1, true);
+        _nftoken.approval(charlie, 3, true);
+        _nftoken.set_approval_for_all(bob, true);
+        _nftoken.set_approval_for_all(dave, true);
+        _nftoken.approve_quantity(charlie, 2);
+        _nftoken.approve_quantity(dave, 1);
+
+        // dave's own grants are untouched
+        env::test::set_caller(dave);
+        _nftoken.set_approval_for_all(charlie, true);
+        _nftoken.approve_quantity(charlie, 1);
+
+        env::test::set_caller(alice);
+        _nftoken.revoke_all_my_approvals();
+
+        assert_eq!(_nftoken.is_approved(1, bob), false);
+        assert_eq!(_nftoken.is_approved(3, charlie), false);
+        assert_eq!(_nftoken.is_approved_for_all(alice, bob), false);
+        assert_eq!(_nftoken.is_approved_for_all(alice, dave), false);
+        assert_eq!(_nftoken.total_active_approvals(), 0);
+        assert_eq!(_nftoken.approval_state(1), (zero_account(), false));
+        assert_eq!(_nftoken.is_approved_for_all(dave, charlie), true);
+        assert_eq!(_nftoken.quantity_approval(alice, charlie), 0);
+        assert_eq!(_nftoken.quantity_approval(alice, dave), 0);
+        assert_eq!(_nftoken.quantity_approval(dave, charlie), 1);
+
+        // the spenders can no longer move the tokens
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.transfer_from(bob, 1), false);
+        assert_eq!(_nftoken.transfer_from(bob, 2), false);
+        env::test::set_caller(charlie);
+        assert_eq!(_nftoken.transfer_from(charlie, 2), false);
+        assert_eq!(_nftoken.owner_of(2), alice);
+    }
+
+    #[test]
+    fn batch_events_follow_input_order() {
+        #[derive(Decode)]
+        struct Transfer {
+            from: AccountId,
+            to: AccountId,
+            token_id: u64,
+        }
+
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.batch_transfer(bob, vec![3, 1, 2]), true);
+
+        let events = env::test::emitted_events().collect::<Vec<_>>();
+        let transfers: Vec<Transfer> = events[events.len() - 3..].iter()
+            .map(|event| Transfer::decode(&mut &event[1..]).unwrap())
+            .collect();
+        assert_eq!(transfers.iter().map(|t| t.token_id).collect::<Vec<u64>>(), vec![3, 1, 2]);
+        assert!(transfers.iter().all(|t| t.from == alice && t.to == bob));
+        assert_eq!(_nftoken.balance_of(bob), 3);
+    }
+
+    #[test]
+    fn deployer_survives_ownership_transfer() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        assert_eq!(_nftoken.deployer(), alice);
+        assert_eq!(_nftoken.transfer_ownership(bob), true);
+        assert_eq!(_nftoken.contract_owner(), bob);
+        assert_eq!(_nftoken.deployer(), alice);
+
+        // the previous owner lost its rights
+        assert_eq!(_nftoken.transfer_ownership(charlie), false);
+        assert_eq!(_nftoken.mint(alice, 1), false);
+
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.mint(bob, 1), true);
+    }
+
+    #[test]
+    fn list_minters_works() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        assert_eq!(_nftoken.grant_minter(bob), true);
+        assert_eq!(_nftoken.grant_minter(charlie), true);
+        assert_eq!(_nftoken.grant_minter(bob), false);
+        assert_eq!(_nftoken.revoke_minter(bob), true);
+        assert_eq!(_nftoken.revoke_minter(bob), false);
+        assert_eq!(_nftoken.list_minters(), vec![charlie]);
+
+        // only the remaining minter can mint
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.mint(bob, 1), false);
+        env::test::set_caller(charlie);
+        assert_eq!(_nftoken.mint(charlie, 1), true);
+        assert_eq!(_nftoken.grant_minter(bob), false);
+    }
+
+    #[test]
+    fn mints_per_block_are_capped() {
+        env::test::set_block_number(1);
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.set_max_mints_per_block(3);
+        assert_eq!(_nftoken.mint(bob, 2), true);
+        assert_eq!(_nftoken.mint(bob, 1), true);
+        // the cap is reached within this block
+        assert_eq!(_nftoken.mint(bob, 1), false);
+        assert_eq!(_nftoken.batch_mint(vec![bob], vec![1]), false);
+
+        // the count resets in the next block
+        env::test::set_block_number(2);
+        assert_eq!(_nftoken.mint(bob, 4), false);
+        assert_eq!(_nftoken.mint(bob, 3), true);
+        assert_eq!(_nftoken.balance_of(bob), 6);
+    }
+
+    #[test]
+    fn token_flags_works() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.freeze(2), true);
+        assert_eq!(_nftoken.burn(3), true);
+
+        assert_eq!(_nftoken.token_flags(1), (true, false, false));
+        assert_eq!(_nftoken.token_flags(2), (true, true, false));
+        assert_eq!(_nftoken.token_flags(3), (false, false, true));
+        assert_eq!(_nftoken.token_flags(4), (false, false, false));
+
+        // a frozen token stays put until unfrozen
+        assert_eq!(_nftoken.transfer(bob, 2), false);
+        assert_eq!(_nftoken.burn(2), false);
+        assert_eq!(_nftoken.unfreeze(2), true);
+        assert_eq!(_nftoken.transfer(bob, 2), true);
+    }
+
+    #[test]
+    fn royalty_is_snapshotted_at_mint() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+
+        _nftoken.set_royalty(500);
+        _nftoken.mint(alice, 1);
+        _nftoken.set_royalty(1000);
+        _nftoken.mint(alice, 1);
+
+        assert_eq!(_nftoken.royalty_of(1), 500);
+        assert_eq!(_nftoken.royalty_of(2), 1000);
+        assert_eq!(_nftoken.royalty_bps(), 1000);
+    }
+
+    #[test]
+    fn quantity_approval_is_used_up() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.approve_quantity(bob, 2), true);
+        assert_eq!(_nftoken.quantity_approval(alice, bob), 2);
+
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.transfer_from(bob, 3), true);
+        assert_eq!(_nftoken.transfer_from(bob, 1), true);
+        assert_eq!(_nftoken.quantity_approval(alice, bob), 0);
+
+        // the third move fails
+        assert_eq!(_nftoken.transfer_from(bob, 2), false);
+        assert_eq!(_nftoken.owner_of(2), alice);
+        assert_eq!(_nftoken.balance_of(bob), 2);
+    }
+
+    #[test]
+    fn minting_can_be_disabled_without_pausing() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.set_minting_enabled(false), true);
+        assert_eq!(_nftoken.minting_enabled(), false);
+        assert_eq!(_nftoken.mint(alice, 1), false);
+        assert_eq!(_nftoken.batch_mint(vec![bob], vec![1]), false);
+        assert_eq!(_nftoken.mint_range(bob, 10, 11), false);
+        // transfers still work
+        assert_eq!(_nftoken.transfer(bob, 1), true);
+
+        assert_eq!(_nftoken.set_minting_enabled(true), true);
+        assert_eq!(_nftoken.mint(alice, 1), true);
+        assert_eq!(_nftoken.total_minted(), 2);
+    }
+
+    #[test]
+    fn cleared_approvals_are_logged_per_token() {
+        #[derive(Decode)]
+        struct Approval {
+            owner: AccountId,
+            spender: AccountId,
+            token_id: u64,
+            approved: bool,
+        }
+
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        _nftoken.approval(charlie, 1, true);
+        _nftoken.approval(charlie, 2, true);
+
+        let events_before = env::test::emitted_events().count();
+        assert_eq!(_nftoken.batch_transfer(bob, vec![1, 2]), true);
+
+        // each token logs its cleared approval followed by its transfer
+        let events = env::test::emitted_events().collect::<Vec<_>>();
+        assert_eq!(events.len() - events_before, 4);
+        for (offset, token_id) in [(4, 1), (2, 2)].iter() {
+            let approval = Approval::decode(&mut &events[events.len() - offset][1..]).unwrap();
+            assert_eq!(approval.owner, alice);
+            assert_eq!(approval.spender, charlie);
+            assert_eq!(approval.token_id, *token_id);
+            assert_eq!(approval.approved, false);
+        }
+        assert_eq!(_nftoken.total_active_approvals(), 0);
+    }
+
+    #[test]
+    fn balance_of_at_reads_past_snapshots() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.snapshot(), 1);
+        _nftoken.transfer(bob, 1);
+        _nftoken.transfer(bob, 2);
+        assert_eq!(_nftoken.snapshot(), 2);
+        _nftoken.transfer(bob, 3);
+
+        assert_eq!(_nftoken.balance_of_at(alice, 1), 3);
+        assert_eq!(_nftoken.balance_of_at(bob, 1), 0);
+        assert_eq!(_nftoken.balance_of_at(alice, 2), 1);
+        assert_eq!(_nftoken.balance_of_at(bob, 2), 2);
+        assert_eq!(_nftoken.balance_of(alice), 0);
+        assert_eq!(_nftoken.balance_of(bob), 3);
+
+        // unknown snapshot
+        assert_eq!(_nftoken.balance_of_at(bob, 3), 0);
+
+        // only the contract owner takes snapshots
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.snapshot(), 0);
+    }
+
+    #[test]
+    fn current_snapshot_id_increments() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+
+        assert_eq!(_nftoken.current_snapshot_id(), 0);
+        _nftoken.snapshot();
+        _nftoken.snapshot();
+        assert_eq!(_nftoken.current_snapshot_id(), 2);
+    }
+
+    #[test]
+    fn max_supply_can_only_be_lowered() {
+        let mut _nftoken = deploy_nftoken(4, 10);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.reduce_max_supply(6), true);
+        // raising, keeping or going below the minted tokens fails
+        assert_eq!(_nftoken.reduce_max_supply(8), false);
+        assert_eq!(_nftoken.reduce_max_supply(6), false);
+        assert_eq!(_nftoken.reduce_max_supply(3), false);
+        assert_eq!(_nftoken.reduce_max_supply(0), false);
+
+        assert_eq!(_nftoken.mint(bob, 3), false);
+        assert_eq!(_nftoken.mint(bob, 2), true);
+        assert_eq!(_nftoken.supply_stats(), (6, 6, 6, 0));
+    }
+
+    #[test]
+    fn transfer_with_price_records_the_sale() {
+        #[derive(Decode)]
+        struct Sale {
+            token_id: u64,
+            from: AccountId,
+            to: AccountId,
+            price: u64,
+        }
+
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.last_sale_of(1), 0);
+        assert_eq!(_nftoken.transfer_with_price(bob, 1, 750), true);
+        assert_eq!(_nftoken.owner_of(1), bob);
+        assert_eq!(_nftoken.last_sale_of(1), 750);
+
+        let event = env::test::emitted_events().last().unwrap();
+        let sale = Sale::decode(&mut &event[1..]).unwrap();
+        assert_eq!((sale.token_id, sale.from, sale.to, sale.price), (1, alice, bob, 750));
+
+        // a failed transfer records nothing
+        assert_eq!(_nftoken.transfer_with_price(bob, 1, 900), false);
+        assert_eq!(_nftoken.last_sale_of(1), 750);
+    }
+
+    #[test]
+    fn is_minter_works() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        _nftoken.grant_minter(bob);
+        assert_eq!(_nftoken.is_minter(alice), true);
+        assert_eq!(_nftoken.is_minter(bob), true);
+        assert_eq!(_nftoken.is_minter(charlie), false);
+    }
+
+    #[test]
+    fn recovery_takes_over_after_the_delay() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        assert_eq!(_nftoken.set_recovery(bob, 500), true);
+
+        // only the recovery account
+        env::test::set_caller(charlie);
+        assert_eq!(_nftoken.initiate_recovery(), false);
+
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.recover_ownership(), false);
+        assert_eq!(_nftoken.initiate_recovery(), true);
+        assert_eq!(_nftoken.recovery(), (bob, 500, 1000));
+
+        // too early
+        env::test::set_now(1499);
+        assert_eq!(_nftoken.recover_ownership(), false);
+        assert_eq!(_nftoken.contract_owner(), alice);
+
+        env::test::set_now(1500);
+        assert_eq!(_nftoken.recover_ownership(), true);
+        assert_eq!(_nftoken.contract_owner(), bob);
+        assert_eq!(_nftoken.recovery(), (zero_account(), 500, 0));
+    }
+
+    #[test]
+    fn owner_can_cancel_a_pending_recovery() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.set_recovery(bob, 500);
+        env::test::set_caller(bob);
+        _nftoken.initiate_recovery();
+
+        env::test::set_caller(alice);
+        assert_eq!(_nftoken.set_recovery(bob, 500), true);
+
+        env::test::set_now(2000);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.recover_ownership(), false);
+        assert_eq!(_nftoken.contract_owner(), alice);
+    }
+
+    #[test]
+    fn transferring_ownership_ends_a_pending_recovery() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        _nftoken.set_recovery(bob, 500);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.initiate_recovery(), true);
+
+        env::test::set_caller(alice);
+        assert_eq!(_nftoken.transfer_ownership(charlie), true);
+        assert_eq!(_nftoken.recovery(), (zero_account(), 500, 0));
+
+        // the old recovery account can neither finish nor restart the recovery
+        env::test::set_now(2000);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.recover_ownership(), false);
+        assert_eq!(_nftoken.initiate_recovery(), false);
+        assert_eq!(_nftoken.contract_owner(), charlie);
+    }
+
+    #[test]
+    fn recent_mints_are_newest_first() {
+        let mut _nftoken = deploy_nftoken(10, 0);
+
+        assert_eq!(_nftoken.recent_mints(3), vec![10, 9, 8]);
+        assert_eq!(_nftoken.recent_mints(20).len(), 10);
+        assert_eq!(_nftoken.recent_mints(0), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn hashlock_claim_with_correct_preimage() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let hashlock = keccak256(b"open sesame");
+
+        assert_eq!(_nftoken.transfer_with_hashlock(bob, 1, hashlock, 2000), true);
+        assert_eq!(_nftoken.hashlock_of(1), (alice, bob, hashlock, 2000));
+
+        #[derive(Decode)]
+        struct Hashlocked {
+            token_id: u64,
+            to: AccountId,
+            hashlock: [u8; 32],
+            expires_at: u64,
+        }
+        let event = env::test::emitted_events().last().unwrap();
+        let locked = Hashlocked::decode(&mut &event[1..]).unwrap();
+        assert_eq!((locked.token_id, locked.to, locked.hashlock, locked.expires_at), (1, bob, hashlock, 2000));
+
+        // escrowed: the owner cannot move it meanwhile
+        assert_eq!(_nftoken.transfer(charlie, 1), false);
+
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.claim(1, b"open sesame".to_vec()), true);
+        assert_eq!(_nftoken.owner_of(1), bob);
+        assert_eq!(_nftoken.hashlock_of(1).0, zero_account());
+    }
+
+    #[test]
+    fn hashlock_rejects_wrong_preimage() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.transfer_with_hashlock(bob, 1, keccak256(b"open sesame"), 2000);
+
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.claim(1, b"open barley".to_vec()), false);
+        assert_eq!(_nftoken.owner_of(1), alice);
+    }
+
+    #[test]
+    fn hashlock_refunds_after_timeout() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        _nftoken.transfer_with_hashlock(bob, 1, keccak256(b"open sesame"), 2000);
+        assert_eq!(_nftoken.refund(1), false);
+
+        // expired: the preimage no longer works and the token is released
+        env::test::set_now(2000);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.claim(1, b"open sesame".to_vec()), false);
+        assert_eq!(_nftoken.refund(1), true);
+
+        env::test::set_caller(alice);
+        assert_eq!(_nftoken.owner_of(1), alice);
+        assert_eq!(_nftoken.transfer(charlie, 1), true);
+    }
+
+    #[test]
+    fn burned_token_cannot_be_approved() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.approval(bob, 1, true);
+        assert_eq!(_nftoken.burn(1), true);
+
+        // the approval went with the token
+        assert_eq!(_nftoken.is_approved(1, bob), false);
+        assert_eq!(_nftoken.total_active_approvals(), 0);
+        assert_eq!(_nftoken.approval(bob, 1, true), false);
+        assert_eq!(_nftoken.approve_until(bob, 1, 100), false);
+        assert_eq!(_nftoken.is_approved(1, bob), false);
+    }
+
+    #[test]
+    fn transfer_cooldown_blocks_quick_flips() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.set_transfer_cooldown(100);
+        env::test::set_now(1100);
+        assert_eq!(_nftoken.transfer(bob, 1), true);
+
+        // right after the transfer
+        env::test::set_caller(bob);
+        env::test::set_now(1150);
+        assert_eq!(_nftoken.transfer(alice, 1), false);
+        assert_eq!(_nftoken.can_transfer(bob, alice, 1), Err(Error::TransferNotAllowed));
+
+        // once the window elapsed
+        env::test::set_now(1200);
+        assert_eq!(_nftoken.transfer(alice, 1), true);
+    }
+
+    #[test]
+    fn paused_operations_report_paused() {
+        let mut _nftoken = deploy_nftoken(1, 2);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.can_mint(1), Ok(()));
+        assert_eq!(_nftoken.can_mint(2), Err(Error::MintNotAllowed));
+
+        _nftoken.pause(0, Vec::new());
+        assert_eq!(_nftoken.can_mint(1), Err(Error::Paused));
+        assert_eq!(_nftoken.can_transfer(alice, bob, 1), Err(Error::Paused));
+        assert_eq!(_nftoken.mint(bob, 1), false);
+        assert_eq!(_nftoken.transfer(bob, 1), false);
+
+        // unrelated failures keep their own error
+        assert_eq!(_nftoken.can_transfer(alice, bob, 7), Err(Error::TokenNotFound));
+
+        _nftoken.unpause();
+        assert_eq!(_nftoken.can_mint(1), Ok(()));
+        assert_eq!(_nftoken.can_transfer(alice, bob, 1), Ok(()));
+    }
+
+    #[test]
+    fn batch_burn_works() {
+        let mut _nftoken = deploy_nftoken(4, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.transfer(bob, 4);
+
+        // bob's token and duplicates fail the whole batch
+        assert_eq!(_nftoken.batch_burn(vec![1, 4]), false);
+        assert_eq!(_nftoken.batch_burn(vec![1, 1]), false);
+        assert_eq!(_nftoken.total_supply(), 4);
+
+        assert_eq!(_nftoken.batch_burn(vec![1, 2, 3]), true);
+        assert_eq!(_nftoken.balance_of(alice), 0);
+        assert_eq!(_nftoken.holds_any(alice), false);
+        assert_eq!(_nftoken.total_supply(), 1);
+        assert_eq!(_nftoken.token_by_index(0), 4);
+        assert_eq!(_nftoken.owner_of(2), zero_account());
+        assert_enumeration_valid(&_nftoken);
+    }
+
+    #[test]
+    fn metadata_can_be_frozen() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+
+        assert_eq!(_nftoken.is_metadata_frozen(), false);
+        assert_eq!(_nftoken.set_token_uri(1, b"ipfs://one".to_vec()), true);
+
+        assert_eq!(_nftoken.freeze_metadata(), true);
+        assert_eq!(_nftoken.is_metadata_frozen(), true);
+        assert_eq!(_nftoken.set_token_uri(1, b"ipfs://other".to_vec()), false);
+        assert_eq!(_nftoken.set_base_uri(b"ipfs://base/".to_vec()), false);
+        assert_eq!(_nftoken.reveal_shuffle([0x42; 32]), false);
+        assert_eq!(_nftoken.token_uri(1), b"ipfs://one".to_vec());
+    }
 
-        // transfer_from function: caller is token owner
-        _nftoken.approval(charlie, 3, true);
-        assert_eq!(_nftoken.is_approved(3, charlie), true);
+    #[test]
+    fn transfers_can_be_restricted_to_operators() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let market = AccountId::try_from([0x8; 32]).unwrap();
 
-        _nftoken.transfer_from(bob, 3);
-        bob_balance = _nftoken.balance_of(bob);
+        _nftoken.set_approval_for_all(market, true);
+        assert_eq!(_nftoken.set_restrict_to_operators(true), true);
 
-        assert_eq!(bob_balance, 2);
+        // the owner cannot move its tokens directly
+        assert_eq!(_nftoken.transfer(bob, 1), false);
+        assert_eq!(_nftoken.transfer_from(bob, 1), false);
+        assert_eq!(_nftoken.batch_transfer(bob, vec![1]), false);
+        assert_eq!(_nftoken.can_transfer(alice, bob, 1), Err(Error::TransferNotAllowed));
+
+        // the operator can
+        env::test::set_caller(market);
+        assert_eq!(_nftoken.transfer_from(bob, 1), true);
+        assert_eq!(_nftoken.owner_of(1), bob);
+
+        env::test::set_caller(alice);
+        assert_eq!(_nftoken.set_restrict_to_operators(false), true);
+        assert_eq!(_nftoken.transfer(bob, 2), true);
+    }
+
+    #[test]
+    fn reveal_range_resolves_per_range() {
+        let mut _nftoken = deploy_nftoken(12, 0);
+
+        _nftoken.set_base_uri(b"ipfs://hidden/".to_vec());
+        assert_eq!(_nftoken.reveal_range(1, 5, b"ipfs://first/".to_vec()), true);
+        assert_eq!(_nftoken.reveal_range(6, 10, b"ipfs://second/".to_vec()), true);
+        // overlapping ranges are rejected
+        assert_eq!(_nftoken.reveal_range(10, 12, b"ipfs://third/".to_vec()), false);
+
+        assert_eq!(_nftoken.token_uri(1), b"ipfs://first/1".to_vec());
+        assert_eq!(_nftoken.token_uri(5), b"ipfs://first/5".to_vec());
+        assert_eq!(_nftoken.token_uri(6), b"ipfs://second/6".to_vec());
+        assert_eq!(_nftoken.token_uri(10), b"ipfs://second/10".to_vec());
+        assert_eq!(_nftoken.token_uri(11), b"ipfs://hidden/11".to_vec());
+    }
+
+    #[test]
+    fn owned_count_agrees_with_balance() {
+        let mut _nftoken = deploy_nftoken(4, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let accounts = [alice, bob, charlie];
+
+        _nftoken.transfer(bob, 1);
+        _nftoken.transfer(bob, 3);
+        _nftoken.mint(charlie, 2);
+        assert_owned_counts_match(&_nftoken, &accounts);
+        assert_enumeration_valid(&_nftoken);
+
+        env::test::set_caller(bob);
+        _nftoken.transfer(charlie, 1);
+        _nftoken.burn(3);
+        assert_owned_counts_match(&_nftoken, &accounts);
+        assert_enumeration_valid(&_nftoken);
+        assert_eq!(_nftoken.owned_count(bob), 0);
+        assert_eq!(_nftoken.owned_count(charlie), 3);
+    }
+
+    #[test]
+    fn single_use_approvals_block_reapproval() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let market = AccountId::try_from([0x8; 32]).unwrap();
+
+        _nftoken.set_consume_approval(true, 100);
+        _nftoken.approval(market, 1, true);
+
+        // the market uses the approval once
+        env::test::set_caller(market);
+        assert_eq!(_nftoken.transfer_from(bob, 1), true);
+        assert_eq!(_nftoken.is_approved(1, market), false);
+
+        // the token comes back, but the market cannot be approved again yet
+        env::test::set_caller(bob);
+        _nftoken.transfer(alice, 1);
+        env::test::set_caller(alice);
+        assert_eq!(_nftoken.approval(market, 1, true), false);
+        assert_eq!(_nftoken.approval(bob, 1, true), true);
+
+        env::test::set_now(1100);
+        assert_eq!(_nftoken.approval(market, 1, true), true);
+        assert_eq!(_nftoken.is_approved(1, market), true);
+    }
+
+    #[test]
+    fn total_revenue_survives_withdrawals() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.set_mint_price(100);
+        _nftoken.set_sale_phase(2);
+
+        env::test::set_caller(bob);
+        env::test::set_value_transferred(250);
+        assert_eq!(_nftoken.public_mint(2), true);
+        assert_eq!(_nftoken.total_revenue(), 200);
+
+        env::test::set_caller(alice);
+        env::test::set_value_transferred(0);
+        assert_eq!(_nftoken.withdraw(), true);
+        assert_eq!(_nftoken.withdraw_payments(), true);
+
+        env::test::set_caller(bob);
+        env::test::set_value_transferred(100);
+        assert_eq!(_nftoken.public_mint(1), true);
+        assert_eq!(_nftoken.total_revenue(), 300);
+    }
+
+    #[test]
+    fn delegated_mint_quota_is_used_up() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.delegate_mint(bob, 3), true);
+        assert_eq!(_nftoken.is_minter(bob), true);
+
+        env::test::set_caller(bob);
+        // more than the quota at once
+        assert_eq!(_nftoken.mint(bob, 4), false);
+        assert_eq!(_nftoken.mint(bob, 2), true);
+        assert_eq!(_nftoken.mint_quota_of(bob), 1);
+        assert_eq!(_nftoken.batch_mint(vec![bob], vec![1]), true);
+        assert_eq!(_nftoken.mint_quota_of(bob), 0);
+
+        // the fourth mint fails
+        assert_eq!(_nftoken.mint(bob, 1), false);
+        assert_eq!(_nftoken.is_minter(bob), false);
+        assert_eq!(_nftoken.balance_of(bob), 3);
+    }
+
+    #[test]
+    fn zero_seed_reveal_uses_block_derived_seed() {
+        let reveal_in_block = |block| {
+            env::test::set_block_number(block);
+            let mut _nftoken = deploy_nftoken(10, 0);
+            assert_eq!(_nftoken.reveal_shuffle([0; 32]), true);
+            (1..=10).map(|token_id| _nftoken.reveal_index_of(token_id)).collect::<Vec<u64>>()
+        };
+
+        let first = reveal_in_block(1);
+        let second = reveal_in_block(2);
+        assert_ne!(first, second);
+
+        // still a permutation
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(sorted, (1..=10).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn auction_bids_refund_and_settle() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let contract = _nftoken.contract_account();
+
+        // the contract must be approved
+        assert_eq!(_nftoken.create_auction(1, 100, 2000), false);
+        _nftoken.approval(contract, 1, true);
+        assert_eq!(_nftoken.create_auction(1, 100, 2000), true);
+        // the token is escrowed meanwhile
+        assert_eq!(_nftoken.transfer(bob, 1), false);
+
+        // below the reserve
+        env::test::set_caller(bob);
+        env::test::set_value_transferred(50);
+        assert_eq!(_nftoken.bid(1), false);
+        assert_eq!(_nftoken.payments_of(bob), 50);
+
+        env::test::set_value_transferred(100);
+        assert_eq!(_nftoken.bid(1), true);
+
+        // outbidding refunds the previous top bidder
+        env::test::set_caller(charlie);
+        env::test::set_value_transferred(100);
+        assert_eq!(_nftoken.bid(1), false);
+        env::test::set_value_transferred(150);
+        assert_eq!(_nftoken.bid(1), true);
+        assert_eq!(_nftoken.payments_of(bob), 150);
+
+        // settling only after the end
+        env::test::set_value_transferred(0);
+        assert_eq!(_nftoken.settle_auction(1), false);
+        env::test::set_now(2000);
+        env::test::set_value_transferred(200);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.bid(1), false);
+        assert_eq!(_nftoken.settle_auction(1), true);
+
+        assert_eq!(_nftoken.owner_of(1), charlie);
+        assert_eq!(_nftoken.payments_of(alice), 150);
+        assert_eq!(_nftoken.settle_auction(1), false);
+    }
+
+    #[test]
+    fn failed_settlement_cancels_the_auction() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let contract = _nftoken.contract_account();
+
+        _nftoken.approval(contract, 1, true);
+        _nftoken.create_auction(1, 100, 2000);
+        env::test::set_caller(bob);
+        env::test::set_value_transferred(100);
+        assert_eq!(_nftoken.bid(1), true);
+        env::test::set_value_transferred(0);
+
+        // the token is frozen mid-auction, so it cannot be handed over
+        env::test::set_caller(alice);
+        assert_eq!(_nftoken.freeze(1), true);
+        env::test::set_now(2000);
+        assert_eq!(_nftoken.settle_auction(1), false);
+
+        // the bid is refundable rather than stuck and the auction is over
+        assert_eq!(_nftoken.owner_of(1), alice);
+        assert_eq!(_nftoken.payments_of(bob), 100);
+        assert_eq!(_nftoken.payments_of(alice), 0);
+        assert_eq!(_nftoken.highest_bid(1), (zero_account(), 0));
+        assert_eq!(_nftoken.settle_auction(1), false);
+    }
+
+    #[test]
+    fn highest_bid_tracks_the_top_bid() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let contract = _nftoken.contract_account();
+
+        _nftoken.set_approval_for_all(contract, true);
+        _nftoken.create_auction(1, 10, 2000);
+        assert_eq!(_nftoken.highest_bid(1), (zero_account(), 0));
+
+        env::test::set_caller(bob);
+        env::test::set_value_transferred(20);
+        _nftoken.bid(1);
+        env::test::set_caller(charlie);
+        env::test::set_value_transferred(30);
+        _nftoken.bid(1);
+
+        assert_eq!(_nftoken.highest_bid(1), (charlie, 30));
+        assert_eq!(_nftoken.highest_bid(2), (zero_account(), 0));
+    }
+
+    #[test]
+    fn attached_tokens_follow_their_parent() {
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let contract = _nftoken.contract_account();
+
+        assert_eq!(_nftoken.attach(2, 2), false);
+        assert_eq!(_nftoken.attach(2, 1), true);
+        assert_eq!(_nftoken.parent_of(2), 1);
+
+        // attaching does not move the token anywhere
+        assert_eq!(_nftoken.owner_of(2), alice);
+        assert_eq!(_nftoken.balance_of(alice), 2);
+        assert_eq!(_nftoken.balance_of(contract), 0);
+        assert_eq!(_nftoken.history_of(2), vec![alice]);
+
+        // an attached token can only move with its parent
+        assert_eq!(_nftoken.transfer(bob, 2), false);
+        assert_eq!(_nftoken.burn(2), false);
+        assert_eq!(_nftoken.burn(1), false);
+        assert_eq!(_nftoken.transfer(bob, 1), true);
+        assert_eq!(_nftoken.owner_of(2), bob);
+        assert_eq!(_nftoken.balance_of(alice), 0);
+        assert_eq!(_nftoken.balance_of(bob), 2);
+        assert_eq!(_nftoken.history_of(2), vec![alice, bob]);
+        assert_eq!(_nftoken.detach(2), false);
+
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.detach(2), true);
+        assert_eq!(_nftoken.parent_of(2), 0);
+        assert_eq!(_nftoken.owner_of(2), bob);
+        assert_eq!(_nftoken.balance_of(bob), 2);
+        assert_eq!(_nftoken.detach(2), false);
+        assert_eq!(_nftoken.transfer(alice, 2), true);
+        assert_owned_counts_match(&_nftoken, &[alice, bob, contract]);
+    }
+
+    #[test]
+    fn attached_tokens_hold_their_parent_in_place() {
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.attach(2, 1), true);
+        _nftoken.freeze(2);
+        assert_eq!(_nftoken.transfer(bob, 1), false);
+        assert_eq!(_nftoken.owner_of(2), AccountId::try_from([0x7; 32]).unwrap());
+
+        _nftoken.unfreeze(2);
+        assert_eq!(_nftoken.transfer(bob, 1), true);
+        assert_eq!(_nftoken.owner_of(2), bob);
+    }
+
+    #[test]
+    fn nested_tokens_resolve_to_the_root_owner() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        // 3 is attached to 2, which is attached to 1
+        assert_eq!(_nftoken.attach(3, 2), true);
+        assert_eq!(_nftoken.attach(2, 1), true);
+        assert_eq!(_nftoken.attach(1, 3), false);
+
+        assert_eq!(_nftoken.transfer(bob, 1), true);
+        assert_eq!(_nftoken.owner_of(2), bob);
+        assert_eq!(_nftoken.owner_of(3), bob);
+        assert_eq!(_nftoken.balance_of(bob), 3);
+        assert_eq!(_nftoken.detach(3), false);
+
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.detach(3), true);
+        assert_eq!(_nftoken.owner_of(3), bob);
+        assert_eq!(_nftoken.balance_of(bob), 3);
+        assert_enumeration_valid(&_nftoken);
+    }
+
+    #[test]
+    fn attach_respects_max_nesting_depth() {
+        let mut _nftoken = deploy_nftoken(8, 0);
+        assert_eq!(_nftoken.max_nesting_depth(), 5);
+
+        // a chain from 1 down to 6 reaches level 5
+        for token_id in 2..7 {
+            assert_eq!(_nftoken.attach(token_id, token_id - 1), true);
+        }
+        assert_eq!(_nftoken.attach(7, 6), false);
+        assert_eq!(_nftoken.attach(7, 5), true);
+
+        // a token with children counts their levels too
+        assert_eq!(_nftoken.attach(8, 7), false);
+        assert_eq!(_nftoken.detach(7), true);
+        assert_eq!(_nftoken.attach(8, 7), true);
+        assert_eq!(_nftoken.attach(7, 5), false);
+        assert_eq!(_nftoken.attach(7, 4), true);
+    }
+
+    #[test]
+    fn can_transfer_now_accounts_for_blocking_state() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let market = AccountId::try_from([0x8; 32]).unwrap();
+
+        assert_eq!(_nftoken.can_transfer_now(alice, 1), true);
+        assert_eq!(_nftoken.can_transfer_now(bob, 1), false);
+        assert_eq!(_nftoken.can_transfer_now(alice, 3), false);
+
+        // frozen
+        _nftoken.freeze(1);
+        assert_eq!(_nftoken.can_transfer_now(alice, 1), false);
+        _nftoken.unfreeze(1);
+
+        // locked by another operator
+        _nftoken.approval(market, 1, true);
+        env::test::set_caller(market);
+        _nftoken.lock_for_sale(1);
+        assert_eq!(_nftoken.can_transfer_now(alice, 1), false);
+        assert_eq!(_nftoken.can_transfer_now(market, 1), true);
+        _nftoken.unlock(1);
+
+        // paused
+        env::test::set_caller(alice);
+        _nftoken.pause(0, Vec::new());
+        assert_eq!(_nftoken.can_transfer_now(alice, 1), false);
+        assert_eq!(_nftoken.is_approved(1, market), true);
+        _nftoken.unpause();
+
+        // cooling down after a transfer
+        _nftoken.set_transfer_cooldown(100);
+        env::test::set_now(1100);
+        assert_eq!(_nftoken.transfer(bob, 2), true);
+        assert_eq!(_nftoken.can_transfer_now(bob, 2), false);
+        env::test::set_now(1200);
+        assert_eq!(_nftoken.can_transfer_now(bob, 2), true);
+        assert_eq!(_nftoken.can_transfer_now(alice, 1), true);
+    }
+
+    #[test]
+    fn burns_fail_when_burning_is_disabled() {
+        env::test::set_caller(AccountId::try_from([0x7; 32]).unwrap());
+        let mut _nftoken = NFToken::deploy_mock(3, 0, b"NFToken".to_vec(), b"NFT".to_vec(), false, 1, 0, None);
+        let market = AccountId::try_from([0x8; 32]).unwrap();
+
+        assert_eq!(_nftoken.burning_enabled(), false);
+        assert_eq!(_nftoken.burn(1), false);
+        assert_eq!(_nftoken.batch_burn(vec![2, 3]), false);
+        _nftoken.set_approval_for_all(market, true);
+        env::test::set_caller(market);
+        assert_eq!(_nftoken.burn_from(1), false);
+
+        assert_eq!(_nftoken.total_supply(), 3);
+    }
+
+    #[test]
+    fn burns_succeed_when_burning_is_enabled() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let market = AccountId::try_from([0x8; 32]).unwrap();
+
+        assert_eq!(_nftoken.burning_enabled(), true);
+        assert_eq!(_nftoken.burn(1), true);
+        assert_eq!(_nftoken.batch_burn(vec![2]), true);
+        env::test::set_caller(market);
+        assert_eq!(_nftoken.burn_from(3), false);
+        env::test::set_caller(alice);
+        _nftoken.set_approval_for_all(market, true);
+        env::test::set_caller(market);
+        assert_eq!(_nftoken.burn_from(3), true);
+
+        assert_eq!(_nftoken.total_supply(), 0);
+        assert_eq!(_nftoken.balance_of(alice), 0);
+    }
+
+    #[test]
+    fn history_of_lists_owners_in_order() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        assert_eq!(_nftoken.history_of(1), vec![alice]);
+        assert_eq!(_nftoken.transfer(bob, 1), true);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.transfer(charlie, 1), true);
+        assert_eq!(_nftoken.history_of(1), vec![alice, bob, charlie]);
+        assert_eq!(_nftoken.history_of(2), Vec::new());
+
+        // only the most recent owners are kept
+        for _ in 0..MAX_HISTORY_LEN {
+            env::test::set_caller(charlie);
+            _nftoken.transfer(bob, 1);
+            env::test::set_caller(bob);
+            _nftoken.transfer(charlie, 1);
+        }
+        let history = _nftoken.history_of(1);
+        assert_eq!(history.len(), MAX_HISTORY_LEN);
+        assert_eq!(history[MAX_HISTORY_LEN - 1], charlie);
+    }
+
+    #[test]
+    fn co_owned_token_needs_every_shareholder() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        _nftoken.transfer(bob, 1);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.set_shares(1, vec![(bob, 6000), (charlie, 3000)]), false);
+        assert_eq!(_nftoken.set_shares(1, vec![(bob, 6000), (bob, 4000)]), false);
+        assert_eq!(_nftoken.set_shares(1, vec![(bob, 6000), (charlie, 4000)]), true);
+        assert_eq!(_nftoken.shares_of(1), vec![(bob, 6000), (charlie, 4000)]);
+
+        // without charlie's agreement
+        assert_eq!(_nftoken.set_share_approval(1, true), true);
+        assert_eq!(_nftoken.transfer(dave, 1), false);
+        env::test::set_caller(dave);
+        assert_eq!(_nftoken.set_share_approval(1, true), false);
+
+        // with every shareholder's agreement
+        env::test::set_caller(charlie);
+        assert_eq!(_nftoken.set_share_approval(1, true), true);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.transfer(dave, 1), true);
+        assert_eq!(_nftoken.owner_of(1), dave);
+        assert_eq!(_nftoken.shares_of(1), Vec::new());
+
+        env::test::set_caller(dave);
+        assert_eq!(_nftoken.transfer(alice, 1), true);
+    }
+
+    #[test]
+    fn is_contract_owner_works() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.is_contract_owner(alice), true);
+        assert_eq!(_nftoken.is_contract_owner(bob), false);
+
+        _nftoken.transfer_ownership(bob);
+        assert_eq!(_nftoken.is_contract_owner(alice), false);
+        assert_eq!(_nftoken.is_contract_owner(bob), true);
+    }
+
+    #[test]
+    fn frozen_token_cannot_move_through_any_entrypoint() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let market = AccountId::try_from([0x8; 32]).unwrap();
+
+        _nftoken.approval(market, 1, true);
+        _nftoken.set_approval_for_all(market, true);
+        _nftoken.freeze(1);
+        assert_eq!(_nftoken.transfer(bob, 1), false);
+        assert_eq!(_nftoken.batch_transfer(bob, vec![1]), false);
+
+        // the operator is authorized: the freeze is what stops it
+        env::test::set_caller(market);
+        assert_eq!(_nftoken.transfer_from(bob, 1), false);
+        assert_eq!(_nftoken.is_approved(1, market), true);
+        assert_eq!(_nftoken.is_approved_for_all(alice, market), true);
+        assert_eq!(_nftoken.can_transfer(alice, bob, 1), Err(Error::TransferNotAllowed));
+
+        env::test::set_caller(alice);
+        _nftoken.unfreeze(1);
+        env::test::set_caller(market);
+        assert_eq!(_nftoken.transfer_from(bob, 1), true);
+        assert_eq!(_nftoken.owner_of(1), bob);
+    }
+
+    #[test]
+    fn token_ids_can_start_at_zero() {
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        env::test::set_caller(alice);
+        let mut _nftoken = NFToken::deploy_mock(2, 0, b"NFToken".to_vec(), b"NFT".to_vec(), true, 0, 0, None);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        assert_eq!(_nftoken.start_token_id(), 0);
+        assert_eq!(_nftoken.token_by_index(0), 0);
+        assert_eq!(_nftoken.owner_of(0), alice);
+        assert_eq!(_nftoken.owner_of(1), alice);
+        assert_eq!(_nftoken.total_supply(), 2);
+
+        assert_eq!(_nftoken.mint(bob, 1), true);
+        assert_eq!(_nftoken.owner_of(2), bob);
+        assert_eq!(_nftoken.transfer(bob, 0), true);
+        assert_eq!(_nftoken.balance_of(bob), 2);
+    }
+
+    #[test]
+    fn transfer_with_tip_credits_the_creator() {
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        _nftoken.grant_minter(bob);
+        _nftoken.set_transfer_fee(5);
+        env::test::set_caller(bob);
+        _nftoken.mint(charlie, 1);
+        assert_eq!(_nftoken.creator_of(1), bob);
+
+        // the fee goes to the fee recipient, the rest to the creator
+        env::test::set_caller(charlie);
+        env::test::set_value_transferred(25);
+        assert_eq!(_nftoken.transfer_with_tip(dave, 1), true);
+        assert_eq!(_nftoken.owner_of(1), dave);
+        assert_eq!(_nftoken.payments_of(alice), 5);
+        assert_eq!(_nftoken.payments_of(bob), 20);
+
+        #[derive(Decode)]
+        struct Tip {
+            token_id: u64,
+            from: AccountId,
+            creator: AccountId,
+            amount: Balance,
+        }
+        let event = env::test::emitted_events().last().unwrap();
+        let tip = Tip::decode(&mut &event[1..]).unwrap();
+        assert_eq!((tip.token_id, tip.from, tip.creator, tip.amount), (1, charlie, bob, 20));
+    }
+
+    #[test]
+    fn zero_tip_is_a_plain_transfer() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        let events_before = env::test::emitted_events().count();
+        assert_eq!(_nftoken.transfer_with_tip(bob, 1), true);
+        assert_eq!(_nftoken.owner_of(1), bob);
+        assert_eq!(_nftoken.payments_of(alice), 0);
+        // only the Transfer event
+        assert_eq!(env::test::emitted_events().count() - events_before, 1);
+    }
+
+    #[test]
+    fn enumerations_stay_valid_across_a_burn() {
+        let mut _nftoken = deploy_nftoken(5, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.transfer(bob, 2);
+        _nftoken.transfer(bob, 4);
+        assert_enumeration_valid(&_nftoken);
+
+        // burning from the middle of both enumerations moves their last entries
+        assert_eq!(_nftoken.burn(1), true);
+        assert_enumeration_valid(&_nftoken);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.burn(2), true);
+        assert_enumeration_valid(&_nftoken);
+
+        assert_eq!(_nftoken.token_of_owner_by_index(bob, 0), 4);
+        assert_eq!(_nftoken.token_of_owner_by_index(bob, 1), 0);
+        assert_eq!(_nftoken.owned_count(alice), 2);
+    }
+
+    #[test]
+    fn has_any_approval_covers_every_mechanism() {
+        let mut _nftoken = deploy_nftoken(2, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let market = AccountId::try_from([0x8; 32]).unwrap();
+
+        // the owner itself
+        assert_eq!(_nftoken.has_any_approval(alice, alice, 1), true);
+
+        // the single-token approval
+        _nftoken.approval(bob, 1, true);
+        assert_eq!(_nftoken.has_any_approval(alice, bob, 1), true);
+        assert_eq!(_nftoken.has_any_approval(alice, bob, 2), false);
+
+        // an operator
+        _nftoken.set_approval_for_all(market, true);
+        assert_eq!(_nftoken.has_any_approval(alice, market, 1), true);
+        assert_eq!(_nftoken.has_any_approval(alice, market, 2), true);
+
+        // nobody else, and never for a token owner does not hold
+        assert_eq!(_nftoken.has_any_approval(alice, charlie, 1), false);
+        assert_eq!(_nftoken.has_any_approval(bob, bob, 1), false);
+        assert_eq!(_nftoken.has_any_approval(alice, alice, 3), false);
+    }
+
+    #[test]
+    fn royalties_cannot_go_below_the_floor() {
+        env::test::set_caller(AccountId::try_from([0x7; 32]).unwrap());
+        let mut _nftoken = NFToken::deploy_mock(1, 0, b"NFToken".to_vec(), b"NFT".to_vec(), true, 1, 250, None);
+
+        assert_eq!(_nftoken.royalty_floor_bps(), 250);
+        assert_eq!(_nftoken.royalty_bps(), 250);
+        assert_eq!(_nftoken.royalty_of(1), 250);
+
+        assert_eq!(_nftoken.set_royalty(100), false);
+        assert_eq!(_nftoken.set_token_royalty(1, 100), false);
+        assert_eq!(_nftoken.royalty_bps(), 250);
+
+        assert_eq!(_nftoken.set_royalty(500), true);
+        assert_eq!(_nftoken.set_token_royalty(1, 500), true);
+        assert_eq!(_nftoken.royalty_bps(), 500);
+        assert_eq!(_nftoken.royalty_of(1), 500);
+        assert_eq!(_nftoken.set_token_royalty(2, 500), false);
+    }
+
+    #[test]
+    fn token_metadata_json_holds_the_attributes() {
+        let mut _nftoken = deploy_nftoken(1, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        _nftoken.set_token_uri(1, b"ipfs://token/1".to_vec());
+        assert_eq!(_nftoken.set_attribute(1, b"color".to_vec(), b"red".to_vec()), true);
+        assert_eq!(_nftoken.set_attribute(1, b"motto".to_vec(), b"say \"hi\"".to_vec()), true);
+        assert_eq!(_nftoken.set_attribute(1, b"color".to_vec(), b"blue".to_vec()), true);
+        assert_eq!(_nftoken.set_attribute(2, b"color".to_vec(), b"red".to_vec()), false);
+        assert_eq!(_nftoken.attributes_of(1).len(), 2);
+
+        let json = _nftoken.token_metadata_json(1);
+        assert_eq!(
+            json,
+            b"{\"name\":\"NFToken #1\",\"uri\":\"ipfs://token/1\",\"attributes\":[\
+              {\"trait_type\":\"color\",\"value\":\"blue\"},\
+              {\"trait_type\":\"motto\",\"value\":\"say \\\"hi\\\"\"}]}".to_vec()
+        );
+        assert_eq!(_nftoken.token_metadata_json(2), Vec::<u8>::new());
+
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.set_attribute(1, b"color".to_vec(), b"green".to_vec()), false);
+    }
+
+    #[test]
+    fn stats_reports_every_counter() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        assert_eq!(_nftoken.stats(), (3, 3, 1, 0, 0));
+
+        _nftoken.set_transfer_fee(5);
+        env::test::set_value_transferred(5);
+        _nftoken.transfer(bob, 1);
+        env::test::set_value_transferred(0);
+        _nftoken.set_transfer_fee(0);
+        _nftoken.approval(charlie, 2, true);
+        _nftoken.burn(3);
+        _nftoken.mint(charlie, 1);
+
+        assert_eq!(_nftoken.stats(), (4, 3, 3, 1, 5));
+    }
+
+    #[test]
+    fn deploy_can_name_another_owner() {
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+        let _nftoken = NFToken::deploy_mock(2, 0, b"NFToken".to_vec(), b"NFT".to_vec(), true, 1, 0, Some(bob));
+
+        assert_eq!(_nftoken.contract_owner(), bob);
+        assert_eq!(_nftoken.deployer(), alice);
+        assert_eq!(_nftoken.balance_of(bob), 2);
+        // the genesis tokens are the owner's creations, not the deployer's
+        assert_eq!(_nftoken.creator_of(1), bob);
+        assert_eq!(_nftoken.creator_of(2), bob);
+    }
+
+    #[test]
+    #[should_panic]
+    fn deploy_rejects_a_zero_owner() {
+        env::test::set_caller(AccountId::try_from([0x7; 32]).unwrap());
+        NFToken::deploy_mock(0, 0, b"NFToken".to_vec(), b"NFT".to_vec(), true, 1, 0, Some(zero_account()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn deploy_rejects_a_zero_caller_as_owner() {
+        env::test::set_caller(zero_account());
+        NFToken::deploy_mock(0, 0, b"NFToken".to_vec(), b"NFT".to_vec(), true, 1, 0, None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn deploy_fails_when_the_initial_mint_fails() {
+        deploy_nftoken(3, 2);
+    }
+
+    #[test]
+    fn set_token_uris_sets_every_uri() {
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let alice = AccountId::try_from([0x7; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let uris = vec![b"ipfs://a".to_vec(), b"ipfs://b".to_vec(), b"ipfs://c".to_vec()];
+
+        // mismatched lengths and unknown tokens set nothing
+        assert_eq!(_nftoken.set_token_uris(vec![1, 2], uris.clone()), false);
+        assert_eq!(_nftoken.set_token_uris(vec![1, 2, 4], uris.clone()), false);
+        assert_eq!(_nftoken.token_uri(1), Vec::<u8>::new());
+
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.set_token_uris(vec![1, 2, 3], uris.clone()), false);
+
+        env::test::set_caller(alice);
+        assert_eq!(_nftoken.set_token_uris(vec![1, 2, 3], uris), true);
+        assert_eq!(_nftoken.token_uri(1), b"ipfs://a".to_vec());
+        assert_eq!(_nftoken.token_uri(2), b"ipfs://b".to_vec());
+        assert_eq!(_nftoken.token_uri(3), b"ipfs://c".to_vec());
+
+        _nftoken.set_max_batch_size(2);
+        assert_eq!(_nftoken.set_token_uris(vec![1, 2, 3], vec![Vec::new(), Vec::new(), Vec::new()]), false);
+    }
+
+    #[test]
+    fn vouchers_mint_once_when_signed_by_the_voucher_signer() {
+        use ed25519_dalek::{Keypair, SecretKey};
+
+        let mut _nftoken = deploy_nftoken(0, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let keypair = |seed: u8| {
+            let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+            let public = PublicKey::from_secret::<Sha512>(&secret);
+            Keypair { secret: secret, public: public }
+        };
+        let signer = keypair(0x4);
+
+        let uri = b"ipfs://token/5".to_vec();
+        let voucher = keccak256(&(_nftoken.contract_account(), bob, 5u64, uri.clone()).encode());
+        let signature = signer.sign::<Sha512>(&voucher).to_bytes().to_vec();
+
+        // nothing can be redeemed before the owner sets a signer, and only the owner can
+        assert_eq!(_nftoken.redeem_voucher(bob, 5, uri.clone(), signature.clone()), false);
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.set_voucher_signer(signer.public.to_bytes()), false);
+        env::test::set_caller(AccountId::try_from([0x7; 32]).unwrap());
+        assert_eq!(_nftoken.set_voucher_signer(signer.public.to_bytes()), true);
+        assert_eq!(_nftoken.voucher_signer(), signer.public.to_bytes());
+
+        // a forged signature, or one for another voucher, mints nothing
+        let forged = keypair(0x5).sign::<Sha512>(&voucher).to_bytes().to_vec();
+        assert_eq!(_nftoken.redeem_voucher(bob, 5, uri.clone(), forged), false);
+        assert_eq!(_nftoken.redeem_voucher(bob, 6, uri.clone(), signature.clone()), false);
+        assert_eq!(_nftoken.redeem_voucher(bob, 5, uri.clone(), b"not a signature".to_vec()), false);
+        assert_eq!(_nftoken.owner_of(5), zero_account());
+
+        // anyone can redeem a valid voucher
+        env::test::set_caller(bob);
+        assert_eq!(_nftoken.redeem_voucher(bob, 5, uri.clone(), signature.clone()), true);
+        assert_eq!(_nftoken.owner_of(5), bob);
+        assert_eq!(_nftoken.token_uri(5), uri);
+        assert_eq!(_nftoken.total_minted(), 1);
+        assert_eq!(_nftoken.is_voucher_used(bob, 5, uri.clone()), true);
+
+        // but only once, even after the token is gone
+        assert_eq!(_nftoken.redeem_voucher(bob, 5, uri.clone(), signature.clone()), false);
+        assert_eq!(_nftoken.burn(5), true);
+        assert_eq!(_nftoken.redeem_voucher(bob, 5, uri, signature), false);
+        assert_eq!(_nftoken.total_minted(), 1);
+    }
+
+    #[test]
+    fn auctioned_or_escrowed_tokens_cannot_be_locked() {
+        env::test::set_now(1000);
+        let mut _nftoken = deploy_nftoken(3, 0);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let market = AccountId::try_from([0x8; 32]).unwrap();
+        let contract = _nftoken.contract_account();
+
+        _nftoken.set_approval_for_all(contract, true);
+        _nftoken.set_approval_for_all(market, true);
+        assert_eq!(_nftoken.create_auction(1, 100, 2000), true);
+        assert_eq!(_nftoken.transfer_with_hashlock(bob, 2, keccak256(b"secret"), 2000), true);
+
+        env::test::set_caller(market);
+        assert_eq!(_nftoken.lock_for_sale(1), false);
+        assert_eq!(_nftoken.lock_for_sale(2), false);
+        assert_eq!(_nftoken.lock_for_sale(3), true);
     }
 }